@@ -1,7 +1,9 @@
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use argh::FromArgs;
+use fitgirl_ddl_lib::export::ExportFormat;
 
 #[derive(FromArgs)]
 #[argh(help_triggers("-h", "--help"))]
@@ -16,6 +18,8 @@ pub struct Cli {
 pub enum Commands {
     Search(Search),
     Fetch(Fetch),
+    Download(Download),
+    Verify(Verify),
 }
 
 /// search games from fitgirl-repacks
@@ -49,11 +53,12 @@ pub struct Search {
 #[derive(FromArgs)]
 #[argh(subcommand, name = "fetch")]
 pub struct Fetch {
-    /// number of workers to spawn
+    /// maximum number of concurrent requests,
+    /// lowered automatically while fuckingfast is rate limiting
     #[argh(option, default = "3")]
     pub workers: usize,
 
-    /// directory to save generated aria2 input files
+    /// directory to save generated files
     #[argh(option, default = "PathBuf::from(\".\")")]
     pub save_dir: PathBuf,
 
@@ -62,6 +67,65 @@ pub struct Fetch {
     #[argh(option)]
     pub cookies: String,
 
+    /// what to write into aria2 input files
+    ///
+    /// acceptable values: "ddl", "magnet", "both"
+    #[argh(option, default = "Source::Ddl")]
+    pub source: Source,
+
+    /// format of the generated files. magnets are only written for aria2,
+    /// metalink has them as alternative sources along with sizes and md5 hashes
+    ///
+    /// acceptable values: "aria2", "urls", "wget", "curl", "bash", "powershell", "metalink",
+    /// "crawljob"
+    #[argh(option, default = "ExportFormat::Aria2")]
+    pub format: ExportFormat,
+
+    /// compare fuckingfast parts with the .torrent file list,
+    /// games with missing, extra or mismatched parts are not written
+    #[argh(switch)]
+    pub check_parts: bool,
+
+    /// game page saved from browser, used when scraping is blocked by ddos-guard.
+    /// can be given multiple times
+    #[argh(option)]
+    pub from_html: Vec<PathBuf>,
+
+    /// how many times to retry rate-limited or failed requests, 0 to disable
+    #[argh(option, default = "3")]
+    pub max_retries: u32,
+
+    /// request every direct link once to report total size and dead links
+    #[argh(switch)]
+    pub probe: bool,
+
+    /// fg-optional or fg-selective group to download, like "fg-selective-english".
+    /// can be given multiple times, all groups are downloaded if none is given
+    #[argh(option)]
+    pub select: Vec<String>,
+
+    /// JSON-RPC endpoint of a running aria2c to send links to,
+    /// like "http://localhost:6800/jsonrpc" or "ws://nas:6800/jsonrpc"
+    #[argh(option)]
+    pub aria2_rpc: Option<String>,
+
+    /// --rpc-secret of aria2c
+    #[argh(option)]
+    pub aria2_secret: Option<String>,
+
+    /// download directory on the aria2 side, each game has its own subdirectory
+    #[argh(option)]
+    pub aria2_dir: Option<String>,
+
+    /// keep polling aria2 and report progress until all sent links finish
+    #[argh(switch)]
+    pub aria2_wait: bool,
+
+    /// send links to a running JDownloader with Click'n'Load,
+    /// one package per game
+    #[argh(switch)]
+    pub click_n_load: bool,
+
     /// url of the game, format is like:
     ///
     /// https://fitgirl-repacks.site/the-bards-tale-iv-directors-cut/
@@ -70,3 +134,101 @@ pub struct Fetch {
     #[argh(positional)]
     pub game_urls: Vec<String>,
 }
+
+/// download games with the built-in downloader, resuming unfinished parts
+#[derive(FromArgs)]
+#[argh(subcommand, name = "download")]
+pub struct Download {
+    /// maximum number of concurrent requests for extracting
+    #[argh(option, default = "3")]
+    pub workers: usize,
+
+    /// number of files to download at once
+    #[argh(option, default = "2")]
+    pub parallel: usize,
+
+    /// number of range requests per file
+    #[argh(option, default = "4")]
+    pub segments: usize,
+
+    /// directory to save games into, each game has its own subdirectory
+    #[argh(option, default = "PathBuf::from(\".\")")]
+    pub save_dir: PathBuf,
+
+    /// cookies for fuckingfast.co, in header value format.
+    /// for example, "cf_clearance=...; dlpass=..."
+    #[argh(option)]
+    pub cookies: String,
+
+    /// how many times to retry rate-limited or failed requests, 0 to disable
+    #[argh(option, default = "3")]
+    pub max_retries: u32,
+
+    /// game page saved from browser, used when scraping is blocked by ddos-guard.
+    /// can be given multiple times
+    #[argh(option)]
+    pub from_html: Vec<PathBuf>,
+
+    /// url of the game, you can type multiple game urls as different arguments
+    #[argh(positional)]
+    pub game_urls: Vec<String>,
+}
+
+/// verify downloaded parts with the md5 file bundled in repacks
+#[derive(FromArgs)]
+#[argh(subcommand, name = "verify")]
+pub struct Verify {
+    /// directory of the downloaded repack
+    #[argh(option, default = "PathBuf::from(\".\")")]
+    pub dir: PathBuf,
+
+    /// number of files to hash at once
+    #[argh(option, default = "4")]
+    pub workers: usize,
+
+    /// url of the game, used to extract fresh links of bad parts
+    #[argh(option)]
+    pub game_url: Option<String>,
+
+    /// game page saved from browser, used in place of --game-url
+    #[argh(option)]
+    pub from_html: Option<PathBuf>,
+
+    /// cookies for fuckingfast.co, in header value format
+    #[argh(option, default = "String::new()")]
+    pub cookies: String,
+
+    /// aria2 input file to write links of bad parts into
+    #[argh(option, default = "PathBuf::from(\"redownload.txt\")")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Ddl,
+    Magnet,
+    Both,
+}
+
+impl Source {
+    pub fn ddl(self) -> bool {
+        matches!(self, Self::Ddl | Self::Both)
+    }
+
+    pub fn magnet(self) -> bool {
+        matches!(self, Self::Magnet | Self::Both)
+    }
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ddl" => Ok(Self::Ddl),
+            "magnet" => Ok(Self::Magnet),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("invalid source: {s}")),
+        }
+    }
+}
@@ -1,29 +1,60 @@
 use std::cmp::Reverse;
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
+use fitgirl_ddl_lib::FitgirlClient;
+use fitgirl_ddl_lib::aria2::Aria2Client;
+use fitgirl_ddl_lib::download::DownloadOptions;
 use fitgirl_ddl_lib::errors::ExtractError;
-use fitgirl_ddl_lib::extract::{DDL, extract_ddl};
-use fitgirl_ddl_lib::scrape::{GameInfo, scrape_game};
+use fitgirl_ddl_lib::export::{
+    Aria2, Crawljob, ExportFormat, Exporter, Metalink, aria2_ddl_input, aria2_magnet_input,
+};
+use fitgirl_ddl_lib::extract::DDL;
+use fitgirl_ddl_lib::http::HeaderValue;
+use fitgirl_ddl_lib::jdownloader::ClickNLoad;
+use fitgirl_ddl_lib::pipeline::{
+    self, EventSender, EventStream, FetchEvent, FetchJob, GameReport, extract_links, scrape_games,
+};
+use fitgirl_ddl_lib::repack::RepackFile;
+use fitgirl_ddl_lib::retry::RetryPolicy;
+use fitgirl_ddl_lib::schedule::{Scheduler, SchedulerConfig};
+use fitgirl_ddl_lib::scrape::GameInfo;
+use fitgirl_ddl_lib::search::{SearchEntry, SearchPage, search_games};
+use fitgirl_ddl_lib::space::{SpacePlan, format_size};
+use fitgirl_ddl_lib::torrent::MagnetLink;
+use fitgirl_ddl_lib::verify::{Checksum, find_md5_file, parse_md5_file, verify_dir};
 use futures_util::StreamExt as _;
-use itertools::Itertools;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 mod args;
-mod search;
 mod utils;
 
-use crate::args::{Cli, Commands, Fetch, Search};
-use crate::search::{SearchEntry, search_games};
-use crate::utils::{display_table, process_time};
+use crate::args::{Cli, Commands, Download, Fetch, Search, Source, Verify};
+use crate::utils::{display_table, format_time};
 
 #[compio::main]
-async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+async fn main() -> Result<ExitCode, Box<dyn Error + Send + Sync>> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .init();
 
-    match argh::from_env::<Cli>().command {
+    let (events, event_stream) = pipeline::channel();
+    let logger = compio::runtime::spawn(log_events(event_stream));
+
+    let result = run(argh::from_env(), &events).await;
+
+    // let the logger catch up before exiting
+    drop(events);
+    _ = logger.await;
+    result
+}
+
+/// Exit code is decided here, exiting is left to [main] so queued events are still logged
+async fn run(cli: Cli, events: &EventSender) -> Result<ExitCode, Box<dyn Error + Send + Sync>> {
+    match cli.command {
         Commands::Search(Search {
             query,
             page,
@@ -31,40 +62,41 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             rich_ui,
             sort_by_date,
         }) => {
-            let results = search_games(&query, page.into()).await?;
-            let mut results = results
-                .into_iter()
-                .take(limit.into())
-                .map(|s| SearchEntry {
-                    date: process_time(&s.date)
-                        .unwrap()
-                        .format("%Y-%m-%d %H:%M:%S")
-                        .to_string(),
-                    ..s
-                })
-                .collect::<Vec<_>>();
+            let SearchPage {
+                entries,
+                page,
+                total_pages,
+                has_next_page,
+            } = search_games(&query, page.into()).await?;
+            let mut results = entries.into_iter().take(limit.into()).collect::<Vec<_>>();
 
             match sort_by_date.as_deref() {
                 None => (),
                 Some("asc") => {
-                    results.sort_by_key(|e| e.date.clone());
+                    results.sort_by_key(|e| e.date);
                 }
                 Some("dsc") => {
-                    results.sort_by_key(|e| Reverse(e.date.clone()));
+                    results.sort_by_key(|e| Reverse(e.date));
                 }
                 Some(order) => warn!("invalid sort order: {order}"),
             }
 
             if rich_ui {
                 display_table(results)?;
-                return Ok(());
+            } else {
+                for SearchEntry { href, title, date } in results {
+                    println!("Title: {title}");
+                    println!("Date: {}", format_time(&date));
+                    println!("Link: {href}");
+                    println!();
+                }
             }
 
-            for SearchEntry { href, title, date } in results {
-                println!("Title: {title}");
-                println!("Date: {date}");
-                println!("Link: {href}");
-                println!();
+            if has_next_page {
+                println!(
+                    "Page {page} of {total_pages}, use --page {} for more",
+                    page + 1
+                );
             }
         }
         Commands::Fetch(Fetch {
@@ -72,87 +104,473 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             save_dir,
             game_urls,
             cookies,
+            source,
+            format,
+            check_parts,
+            from_html,
+            max_retries,
+            probe,
+            select,
+            aria2_rpc,
+            aria2_secret,
+            aria2_dir,
+            aria2_wait,
+            click_n_load,
         }) => {
             info!("workers: {workers}, save_dir: {save_dir:?}");
             compio::fs::create_dir_all(&save_dir).await?;
 
-            let scrape_results: Vec<_> = futures_util::stream::iter(game_urls)
-                .map(|game_url| {
-                    info!("processing {game_url}");
-                    async move {
-                        scrape_game(&game_url)
-                            .await
-                            .inspect_err(|e| {
-                                error!("failed to scrape {game_url}: {e}");
-                            })
-                            .ok()
+            let retry = RetryPolicy {
+                max_attempts: max_retries + 1,
+                ..Default::default()
+            };
+
+            let scheduler = Scheduler::new(SchedulerConfig {
+                max_concurrency: workers,
+                ..Default::default()
+            });
+            let client = FitgirlClient::builder()
+                .fuckingfast_cookies(HeaderValue::from_str(&cookies)?)
+                .scheduler(scheduler)
+                .build();
+
+            let aria2 = aria2_rpc.map(|endpoint| Aria2Client::new(endpoint, aria2_secret));
+            let jdownloader = ClickNLoad::default();
+            if click_n_load && !jdownloader.is_running().await {
+                return Err(format!(
+                    "JDownloader is not listening on {}",
+                    ClickNLoad::DEFAULT_ENDPOINT
+                )
+                .into());
+            }
+
+            let mut job = FetchJob::new(&client)
+                .retry(retry.clone())
+                .events(events.clone())
+                .workers(workers)
+                .urls(game_urls)
+                .select(select)
+                .extract(source.ddl())
+                .fetch_torrent(format == ExportFormat::Metalink)
+                .skip_incomplete(check_parts)
+                .probe(probe)
+                .output_dir(&save_dir)
+                .exporter_with(|game| game_exporter(format, source, game, &save_dir));
+            for path in from_html {
+                match compio::fs::read(&path).await {
+                    Ok(html) => {
+                        job = job.page(path.display().to_string(), String::from_utf8_lossy(&html));
                     }
-                })
-                .buffer_unordered(workers)
-                .collect()
-                .await;
+                    Err(e) => events.send(FetchEvent::ScrapeFailed {
+                        url: path.display().to_string(),
+                        error: e.to_string(),
+                    }),
+                }
+            }
+            let report = job.run().await;
 
-            for result in scrape_results {
-                let Some(GameInfo {
+            let mut gids = Vec::new();
+            for game_report in &report.games {
+                let GameInfo {
                     path_part,
-                    fuckingfast_links,
-                }) = result
-                else {
+                    torrent_files,
+                    metadata,
+                    ..
+                } = &game_report.game;
+
+                if game_report.skipped {
+                    error!("skipped {path_part}, parts are incomplete");
                     continue;
-                };
+                }
+                if check_parts && torrent_files.is_empty() {
+                    warn!("no .torrent for {path_part}, skipped checking");
+                }
+
+                for filename in &game_report.unselected {
+                    let file = RepackFile::parse(filename);
+                    info!(
+                        "skipped {} of {:?} group {} ({})",
+                        file.filename,
+                        file.kind,
+                        file.group,
+                        metadata.describe_group(&file.group)
+                    );
+                }
+
+                let results = &game_report.ddls;
+                for ddl in results {
+                    if let Some(info) = &ddl.probe
+                        && !info.is_alive()
+                    {
+                        warn!("dead link for {}: HTTP {}", ddl.filename, info.status);
+                    }
+                }
+
+                let plan = SpacePlan::new(metadata, results);
+                if probe && !plan.download_exact {
+                    warn!("{path_part}: some parts failed probing, using the repack size");
+                }
+                info!("{path_part}: {plan}");
+                match plan.check(&save_dir) {
+                    Ok((available, false)) => warn!(
+                        "{path_part}: needs {} but only {} free in {save_dir:?}",
+                        format_size(plan.required()),
+                        format_size(available)
+                    ),
+                    Ok(_) => (),
+                    Err(e) => warn!("failed to get free space of {save_dir:?}: {e}"),
+                }
+
+                if let Some(e) = &game_report.write_error {
+                    error!("failed to save {path_part}: {e}");
+                }
+
+                if click_n_load {
+                    match jdownloader.add_ddls(path_part, results).await {
+                        Ok(_) => {
+                            info!("sent {} links of {path_part} to JDownloader", results.len())
+                        }
+                        Err(e) => error!("failed to send {path_part} to JDownloader: {e}"),
+                    }
+                }
+
+                if let Some(aria2) = &aria2 {
+                    let dir = aria2_dir
+                        .as_ref()
+                        .map(|dir| format!("{}/{path_part}", dir.trim_end_matches('/')));
+                    for ddl in results {
+                        match retry
+                            .run(async || aria2.add_ddl(ddl, dir.as_deref()).await)
+                            .await
+                        {
+                            Ok(gid) => {
+                                info!("sent {} to aria2 as {gid}", ddl.filename);
+                                gids.push(gid);
+                            }
+                            Err(e) => error!("failed to send {} to aria2: {e}", ddl.filename),
+                        }
+                    }
+                }
+            }
+
+            let missing_files: Vec<_> = report.missing_files().collect();
+            if !missing_files.is_empty() {
+                warn!(
+                    "{} files not found or deleted:\n{}",
+                    missing_files.len(),
+                    missing_files.join("\n")
+                );
+            }
+            if report.rate_limited {
+                return Err("still rate limited after retrying, later games were skipped".into());
+            }
+
+            if let Some(aria2) = &aria2
+                && aria2_wait
+                && !gids.is_empty()
+            {
+                let statuses = aria2
+                    .watch(&gids, Duration::from_secs(5), |statuses| {
+                        let done = statuses.iter().filter(|s| s.is_finished()).count();
+                        let completed: u64 = statuses.iter().map(|s| s.completed_length).sum();
+                        let total: u64 = statuses.iter().map(|s| s.total_length).sum();
+                        let speed: u64 = statuses.iter().map(|s| s.download_speed).sum();
+                        info!(
+                            "aria2: {done}/{} files, {} of {}, {}/s",
+                            statuses.len(),
+                            format_size(completed),
+                            format_size(total),
+                            format_size(speed)
+                        );
+                    })
+                    .await?;
+                for status in statuses.iter().filter(|s| s.status != "complete") {
+                    error!(
+                        "aria2 {} {}: {}",
+                        status.gid,
+                        status.status,
+                        status.error_message.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+        }
+        Commands::Download(Download {
+            workers,
+            parallel,
+            segments,
+            save_dir,
+            cookies,
+            max_retries,
+            from_html,
+            game_urls,
+        }) => {
+            let retry = RetryPolicy {
+                max_attempts: max_retries + 1,
+                ..Default::default()
+            };
+            let scheduler = Scheduler::new(SchedulerConfig {
+                max_concurrency: workers,
+                ..Default::default()
+            });
+            let client = FitgirlClient::builder()
+                .fuckingfast_cookies(HeaderValue::from_str(&cookies)?)
+                .scheduler(scheduler)
+                .build();
+            let options = DownloadOptions {
+                segments,
+                ..Default::default()
+            };
 
-                let output_file = save_dir.join(format!("{path_part}.txt"));
+            let games = scrape_all(&client, &retry, game_urls, from_html, workers, events).await;
 
-                info!("start extracting for {path_part}");
+            let mut failed = Vec::new();
+            for game in games {
+                let links = game.mirror_links(client.hosters());
+                let path_part = game.path_part;
+                let game_dir = save_dir.join(&path_part);
+                compio::fs::create_dir_all(&game_dir).await?;
 
-                let ddls: Vec<_> = futures_util::stream::iter(fuckingfast_links)
-                    .map(|ff_url| {
-                        info!("processing {ff_url}");
-                        let cookies = cookies.clone();
+                let ddls = extract_all(&client, &retry, &path_part, links, workers, events).await?;
+
+                info!("downloading {} files into {game_dir:?}", ddls.len());
+                let results: Vec<_> = futures_util::stream::iter(&ddls)
+                    .map(|ddl| {
+                        let (client, retry, options, game_dir) =
+                            (&client, &retry, &options, &game_dir);
                         async move {
-                            extract_ddl(&ff_url, &cookies).await.inspect_err(|e| {
-                                error!("failed to extract {ff_url}: {e}");
-                            })
+                            let result = retry
+                                .run(async || client.download(ddl, game_dir, options).await)
+                                .await;
+                            (ddl, result)
                         }
                     })
-                    .buffer_unordered(workers)
+                    .buffer_unordered(parallel)
                     .collect()
                     .await;
 
-                let mut results = Vec::with_capacity(ddls.len());
-                for result in ddls {
-                    match result {
-                        Err(ExtractError::RateLimited) => {
-                            info!("early-exiting due to rate-limited error!");
-                            std::process::exit(1);
-                        }
-                        Ok(result) => results.push(result),
-                        _ => continue,
+                for (ddl, result) in results {
+                    if let Err(e) = result {
+                        error!("failed to download {}: {e}", ddl.filename);
+                        failed.push(format!("{path_part}/{}", ddl.filename));
                     }
                 }
+            }
+
+            if !failed.is_empty() {
+                error!(
+                    "{} files failed, run again to resume:\n{}",
+                    failed.len(),
+                    failed.join("\n")
+                );
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Commands::Verify(Verify {
+            dir,
+            workers,
+            game_url,
+            from_html,
+            cookies,
+            output,
+        }) => {
+            let report = verify_dir(&dir, workers).await?;
+            if report.is_ok() {
+                info!("{report}");
+                return Ok(ExitCode::SUCCESS);
+            }
+            error!("bad parts in {dir:?}:\n{report}");
+
+            if game_url.is_some() || from_html.is_some() {
+                let retry = RetryPolicy::default();
+                let client = FitgirlClient::builder()
+                    .fuckingfast_cookies(HeaderValue::from_str(&cookies)?)
+                    .build();
 
-                let output_string: String = results
+                let games = scrape_all(
+                    &client,
+                    &retry,
+                    game_url.into_iter().collect(),
+                    from_html.into_iter().collect(),
+                    1,
+                    events,
+                )
+                .await;
+                let links: Vec<String> = games
                     .iter()
-                    .sorted_by(|&a, &b| a.filename.cmp(&b.filename))
-                    .map(
-                        |DDL {
-                             filename,
-                             direct_link,
-                         }| {
-                            format!(
-                                "{direct_link}
-    out={filename}
-    continue=true
-"
-                            )
-                        },
-                    )
+                    .flat_map(|game| game.mirror_links(client.hosters()))
                     .collect();
+                let links: Vec<String> = report.select_links(&links).into_iter().cloned().collect();
+
+                let workers = client.scheduler().config().max_concurrency;
+                let ddls = extract_all(
+                    &client,
+                    &retry,
+                    &dir.to_string_lossy(),
+                    links,
+                    workers,
+                    events,
+                )
+                .await?;
+                // redownloaded parts replace the bad ones, wherever aria2 is started
+                let target = std::path::absolute(&dir).unwrap_or_else(|_| dir.clone());
+                let input = aria2_ddl_input(&ddls, Some(&target.to_string_lossy()));
+                compio::fs::write(&output, input.into_bytes()).await.0?;
+                info!("saved links of {} bad parts to {output:?}", ddls.len());
+            }
+
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Scrape `game_urls` and parse saved pages, games which failed are logged and skipped
+async fn scrape_all(
+    client: &FitgirlClient,
+    retry: &RetryPolicy,
+    game_urls: Vec<String>,
+    from_html: Vec<PathBuf>,
+    workers: usize,
+    events: &EventSender,
+) -> Vec<GameInfo> {
+    let mut games: Vec<_> = scrape_games(client, retry, game_urls, workers, events)
+        .await
+        .into_iter()
+        .filter_map(|(_, result)| result.ok())
+        .collect();
+
+    for path in from_html {
+        info!("parsing {path:?}");
+        let result = match compio::fs::read(&path).await {
+            Ok(html) => client
+                .parse_game_page(&String::from_utf8_lossy(&html), None)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(game) => games.push(game),
+            Err(error) => events.send(FetchEvent::ScrapeFailed {
+                url: path.display().to_string(),
+                error,
+            }),
+        }
+    }
+
+    games
+}
+
+/// Extract links of `path_part`, fails if still rate limited after retrying
+async fn extract_all(
+    client: &FitgirlClient,
+    retry: &RetryPolicy,
+    path_part: &str,
+    links: Vec<String>,
+    workers: usize,
+    events: &EventSender,
+) -> Result<Vec<DDL>, ExtractError> {
+    let extracted = extract_links(client, retry, path_part, links, workers, events).await;
+    if !extracted.rate_limited.is_empty() {
+        info!("early-exiting due to rate-limited error, retries exhausted!");
+        return Err(ExtractError::RateLimited);
+    }
+    Ok(extracted.ddls)
+}
 
-                let _ = compio::fs::write(output_file, output_string.into_bytes()).await;
+/// Log progress of the pipeline until every sender is dropped
+async fn log_events(mut events: EventStream) {
+    while let Some(event) = events.next().await {
+        match event {
+            FetchEvent::ScrapeStarted { url } => info!("processing {url}"),
+            FetchEvent::ScrapeFinished {
+                path_part, links, ..
+            } => info!("found {links} links for {path_part}"),
+            FetchEvent::ScrapeFailed { url, error } => error!("failed to scrape {url}: {error}"),
+            FetchEvent::ExtractPlanned { games, links } => {
+                info!("extracting {links} links of {games} games")
+            }
+            FetchEvent::PartsChecked { path_part, report } if !report.is_incomplete() => {
+                info!("{path_part}: {report}")
+            }
+            FetchEvent::PartsChecked { path_part, report } => {
+                warn!("incomplete parts for {path_part}:\n{report}")
             }
+            FetchEvent::ExtractStarted { path_part, links } => {
+                info!("start extracting {links} links for {path_part}")
+            }
+            FetchEvent::LinkResolved { url, ddl } => debug!("resolved {url} to {}", ddl.filename),
+            FetchEvent::LinkFailed { url, error } => error!("failed to extract {url}: {error}"),
+            FetchEvent::FileMissing { filename, .. } => warn!("missing file: {filename}"),
+            FetchEvent::RateLimited { url } => error!("still rate limited on {url}"),
+            FetchEvent::GameWritten { path, .. } => info!("saved: {path:?}"),
+        }
+    }
+}
+
+/// Checksums from the `.md5` file of an earlier download into `dir`, empty if there is none
+fn read_checksums(dir: &Path) -> Vec<Checksum> {
+    let md5_file = match find_md5_file(dir) {
+        Ok(Some(md5_file)) => md5_file,
+        Ok(None) => return Vec::new(),
+        Err(e) => {
+            warn!("failed to look for md5 file in {dir:?}: {e}");
+            return Vec::new();
+        }
+    };
+    match std::fs::read(&md5_file) {
+        Ok(bytes) => parse_md5_file(&String::from_utf8_lossy(&bytes)),
+        Err(e) => {
+            warn!("failed to read {md5_file:?}: {e}");
+            Vec::new()
         }
     }
+}
+
+/// Exporter of `format` for a game, with its own magnets, torrent and package name if supported
+fn game_exporter(
+    format: ExportFormat,
+    source: Source,
+    game: &GameReport,
+    save_dir: &Path,
+) -> Box<dyn Exporter> {
+    let GameInfo {
+        path_part, magnets, ..
+    } = &game.game;
+
+    // metalink has magnets as alternative sources of each file
+    if source.magnet() && format != ExportFormat::Metalink {
+        if magnets.is_empty() {
+            warn!("no magnet found for {path_part}");
+        } else if format != ExportFormat::Aria2 {
+            warn!("skipping magnets of {path_part}, only aria2 input supports them");
+        } else {
+            return Box::new(Aria2WithMagnets(magnets.clone()));
+        }
+    }
+
+    match format {
+        ExportFormat::Metalink => Box::new(Metalink {
+            checksums: read_checksums(&save_dir.join(path_part)),
+            magnet: magnets.first().cloned(),
+            torrent: game.torrent.as_ref().map(|(_, info)| info.clone()),
+        }),
+        ExportFormat::Crawljob => Box::new(Crawljob {
+            package_name: Some(path_part.clone()),
+        }),
+        _ => Box::new(format),
+    }
+}
+
+/// aria2 input with magnets of the game after its direct links
+struct Aria2WithMagnets(Vec<MagnetLink>);
 
-    Ok(())
+impl Exporter for Aria2WithMagnets {
+    fn extension(&self) -> &'static str {
+        Aria2.extension()
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        Aria2.write_ddls(output, ddls, dir);
+        *output += &aria2_magnet_input(&self.0, dir);
+    }
 }
@@ -1,12 +1,12 @@
 use std::error::Error;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local};
+use fitgirl_ddl_lib::search::SearchEntry;
 
-use crate::search::SearchEntry;
-
-pub fn process_time(rfc3339: &str) -> Result<DateTime<Local>, Box<dyn Error + Send + Sync>> {
-    let dt = DateTime::parse_from_rfc3339(rfc3339).map_err(|_| "invalid time format")?;
-    Ok(dt.to_utc().with_timezone(&Local))
+pub fn format_time(dt: &DateTime<FixedOffset>) -> String {
+    dt.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
 }
 
 pub fn display_table(
@@ -35,7 +35,7 @@ pub fn display_table(
             title = title.chars().take(string_len_limit - 3).collect::<String>() + "...";
         }
 
-        table.add_row_cells(["date", &date]);
+        table.add_row_cells(["date", &format_time(&date)]);
         table.add_row_cells(["game", &title]);
         table.add_row_cells(["link", &href]);
         table.add_section();
@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
 use std::fmt::Write;
 
+use fitgirl_ddl_lib::errors::ExtractError;
 use fitgirl_ddl_lib::extract::DDL;
 use fitgirl_ddl_lib::http::HeaderValue;
-use fitgirl_ddl_lib::set_fg_cookies;
+use fitgirl_ddl_lib::pipeline::FetchJob;
+use fitgirl_ddl_lib::scrape::GameMetadata;
+use fitgirl_ddl_lib::torrent::TorrentInfo;
 use itertools::Itertools;
 use tracing::{debug, error, info, warn};
 
@@ -13,13 +16,16 @@ use winio::prelude::*;
 use crate::Result;
 use crate::model::Cookie;
 use crate::ui::select_box::{SelectEvent, SelectWindow};
-use crate::utils::{ExtractionInfo, centralize_window, collect_groups, export_ddl};
+use crate::utils::{
+    ExtractionInfo, centralize_window, client, collect_groups, export_ddl, export_games,
+};
 
 #[allow(unused)]
 pub struct MainModel {
     window: Child<Window>,
     selective_boxes: BTreeMap<usize, Child<SelectWindow>>,
     button: Child<Button>,
+    open_button: Child<Button>,
     url_edit: Child<TextBox>,
     progress: Child<Progress>,
     position: usize,
@@ -31,10 +37,16 @@ pub enum MainMessage {
     Close,
     Redraw,
     Download,
+    OpenPage,
     DownloadDone,
     IncreaseCount,
     SetMaxCap(usize),
-    CreateSelection(Vec<DDL>, String),
+    CreateSelection(
+        Vec<DDL>,
+        String,
+        Option<(String, TorrentInfo)>,
+        GameMetadata,
+    ),
     CloseSelective(usize),
 }
 
@@ -54,6 +66,9 @@ impl Component for MainModel {
             button: Button = (&window) => {
                 text: " Scrape ",
             },
+            open_button: Button = (&window) => {
+                text: " Open Page ",
+            },
             progress: Progress = (&window) => {
                 minimum: 0,
                 maximum: 1,
@@ -72,7 +87,7 @@ impl Component for MainModel {
                 .iter()
                 .map(|Cookie { name, value }| format!("{name}={value}"))
                 .join("; ");
-            _ = set_fg_cookies(HeaderValue::from_str(&cookies)?);
+            client().set_fitgirl_cookies(HeaderValue::from_str(&cookies)?);
             Result::Ok(())
         })
         .await
@@ -89,6 +104,7 @@ impl Component for MainModel {
             window,
             url_edit,
             button,
+            open_button,
             progress,
             position: 0,
             selective_boxes: BTreeMap::default(),
@@ -106,6 +122,9 @@ impl Component for MainModel {
                 self.button => {
                     ButtonEvent::Click => MainMessage::Download,
                 },
+                self.open_button => {
+                    ButtonEvent::Click => MainMessage::OpenPage,
+                },
                 self.url_edit => {
                     TextBoxEvent::Change => MainMessage::Redraw,
                 },
@@ -159,6 +178,7 @@ impl Component for MainModel {
             }
             MainMessage::DownloadDone => {
                 self.button.enable()?;
+                self.open_button.enable()?;
                 Ok(false)
             }
             MainMessage::Download => {
@@ -173,6 +193,7 @@ impl Component for MainModel {
                 let sender = sender.clone();
 
                 self.button.disable()?;
+                self.open_button.disable()?;
 
                 // reset range
                 self.progress.set_pos(0)?;
@@ -180,47 +201,49 @@ impl Component for MainModel {
 
                 spawn(async move {
                     let urls = text.split([' ', '\n', '\t']).filter(|s| !s.is_empty());
-                    let export = export_ddl(urls, 2, &sender, selective).await;
+                    let export = export_ddl(urls, &sender, selective).await;
+                    show_extraction_info(export);
+
+                    sender.post(MainMessage::DownloadDone);
+                })
+                .detach();
+
+                Ok(false)
+            }
+            MainMessage::OpenPage => {
+                let Some(path) = FileBox::new()
+                    .title("Open saved game page")
+                    .add_filter(("HTML", "*.html;*.htm"))
+                    .open(Some(self.window.as_window()))
+                    .await?
+                else {
+                    return Ok(false);
+                };
+                info!("parsing saved page: {path:?}");
 
-                    match export {
+                let sender = sender.clone();
+
+                self.button.disable()?;
+                self.open_button.disable()?;
+                self.progress.set_pos(0)?;
+
+                spawn(async move {
+                    match compio::fs::read(&path).await {
+                        Ok(html) => {
+                            let job = FetchJob::new(client())
+                                .page(path.display().to_string(), String::from_utf8_lossy(&html));
+                            let export = export_games(job, &sender, true).await;
+                            show_extraction_info(export);
+                        }
                         Err(e) => {
                             popup_message(
                                 (),
-                                format!("failed to scrape: {e}"),
+                                format!("failed to read {path:?}: {e}"),
                                 MessageBoxStyle::Error,
                             )
                             .await
                             .ok();
                         }
-                        Ok(ExtractionInfo {
-                            missing_files,
-                            scrape_errors,
-                            ..
-                        }) => {
-                            let missing = missing_files.join("\n");
-                            let errors = scrape_errors.join("\n");
-
-                            let mut message = String::new();
-
-                            if !missing.is_empty() {
-                                _ = message.write_fmt(format_args!(
-                                    "File Not Found Or Deleted:\n{missing}\n"
-                                ));
-                            }
-                            if !errors.is_empty() {
-                                _ = message.write_fmt(format_args!("Failed:\n{errors}"));
-                            }
-
-                            compio::runtime::spawn(async move {
-                                let message = message.trim();
-                                if !message.is_empty() {
-                                    popup_message((), message.trim(), MessageBoxStyle::Warning)
-                                        .await
-                                        .ok();
-                                }
-                            })
-                            .detach();
-                        }
                     }
 
                     sender.post(MainMessage::DownloadDone);
@@ -247,9 +270,14 @@ impl Component for MainModel {
 
                 Ok(false)
             }
-            MainMessage::CreateSelection(ddls, game_name) => {
-                let swindow =
-                    Child::<SelectWindow>::init((collect_groups(ddls), game_name)).await?;
+            MainMessage::CreateSelection(ddls, game_name, torrent, metadata) => {
+                let swindow = Child::<SelectWindow>::init((
+                    collect_groups(ddls),
+                    game_name,
+                    torrent,
+                    metadata,
+                ))
+                .await?;
                 let window_id = swindow.window_id;
 
                 self.selective_boxes.insert(window_id, swindow);
@@ -267,6 +295,7 @@ impl Component for MainModel {
             StackPanel::new(Orient::Horizontal),
             self.url_edit => { grow: true },
             self.button,
+            self.open_button,
         };
         let mut layout_final = layout! {
             StackPanel::new(Orient::Vertical),
@@ -286,6 +315,53 @@ impl Component for MainModel {
     }
 }
 
+fn show_extraction_info(export: std::result::Result<ExtractionInfo, ExtractError>) {
+    let message = match export {
+        Err(e) => {
+            spawn(async move {
+                popup_message((), format!("failed to scrape: {e}"), MessageBoxStyle::Error)
+                    .await
+                    .ok();
+            })
+            .detach();
+            return;
+        }
+        Ok(ExtractionInfo {
+            missing_files,
+            scrape_errors,
+            incomplete_games,
+            ..
+        }) => {
+            let missing = missing_files.join("\n");
+            let errors = scrape_errors.join("\n");
+            let incomplete = incomplete_games.join("\n");
+
+            let mut message = String::new();
+
+            if !missing.is_empty() {
+                _ = message.write_fmt(format_args!("File Not Found Or Deleted:\n{missing}\n"));
+            }
+            if !incomplete.is_empty() {
+                _ = message.write_fmt(format_args!("Incomplete Parts:\n{incomplete}\n"));
+            }
+            if !errors.is_empty() {
+                _ = message.write_fmt(format_args!("Failed:\n{errors}"));
+            }
+            message
+        }
+    };
+
+    spawn(async move {
+        let message = message.trim();
+        if !message.is_empty() {
+            popup_message((), message.trim(), MessageBoxStyle::Warning)
+                .await
+                .ok();
+        }
+    })
+    .detach();
+}
+
 async fn popup_message(
     parent: impl Into<MaybeBorrowedWindow<'_>>,
     message: impl AsRef<str>,
@@ -1,9 +1,15 @@
+use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 
 use ahash::AHashMap;
+use fitgirl_ddl_lib::export::aria2_torrent_input;
 use fitgirl_ddl_lib::extract::DDL;
+use fitgirl_ddl_lib::repack::{RepackFile, natural_cmp};
+use fitgirl_ddl_lib::scrape::GameMetadata;
+use fitgirl_ddl_lib::space::{SpacePlan, format_size};
+use fitgirl_ddl_lib::torrent::TorrentInfo;
 use itertools::Itertools;
-use tracing::debug;
+use tracing::{debug, error, info, warn};
 use winio::prelude::*;
 
 use crate::Result;
@@ -16,10 +22,19 @@ pub struct SelectWindow {
     pub window: Child<Window>,
     pub scroll: Child<ScrollView>,
     pub checkbox: Vec<Child<CheckBox>>,
+    /// Group of each checkbox, whose text is a description
+    pub checkbox_groups: Vec<String>,
+    /// [SpacePlan] of the checked groups and free space of `target_dir`
+    pub space: Child<Label>,
     pub submit: Child<Button>,
 
     pub game_name: String,
+    /// Absolute directory the files are downloaded into
+    pub target_dir: PathBuf,
     pub groups: AHashMap<String, Vec<DDL>>,
+    pub metadata: GameMetadata,
+    /// `.torrent` url and its file list
+    pub torrent: Option<(String, TorrentInfo)>,
 }
 
 #[derive(Debug, Clone)]
@@ -27,6 +42,7 @@ pub enum SelectMessage {
     Noop,
     CloseWindow,
     Refresh,
+    UpdateSpace,
     SaveFile,
 }
 
@@ -38,12 +54,17 @@ static SWINDOW_ID: AtomicUsize = AtomicUsize::new(0);
 
 impl Component for SelectWindow {
     type Error = color_eyre::Report;
-    type Init<'a> = (AHashMap<String, Vec<DDL>>, String);
+    type Init<'a> = (
+        AHashMap<String, Vec<DDL>>,
+        String,
+        Option<(String, TorrentInfo)>,
+        GameMetadata,
+    );
     type Message = SelectMessage;
     type Event = SelectEvent;
 
     async fn init(
-        (groups, game_name): Self::Init<'_>,
+        (groups, game_name, torrent, metadata): Self::Init<'_>,
         sender: &ComponentSender<Self>,
     ) -> Result<Self> {
         init! {
@@ -55,6 +76,7 @@ impl Component for SelectWindow {
                 vscroll: true,
                 hscroll: false,
             },
+            space: Label = (&window),
             submit: Button = (&window) => {
                 text: "Confirm",
             },
@@ -63,31 +85,46 @@ impl Component for SelectWindow {
         centralize_window(&mut window)?;
 
         let mut checkbox = Vec::with_capacity(groups.len());
-        for group_name in groups.keys().sorted() {
+        let mut checkbox_groups = Vec::with_capacity(groups.len());
+        for (group_name, ddls) in groups.iter().sorted_by(|a, b| natural_cmp(a.0, b.0)) {
+            let kind = ddls
+                .first()
+                .map(|ddl| RepackFile::parse(&ddl.filename).kind);
+            let text = if kind.is_some_and(|kind| kind.is_optional()) {
+                format!("{} ({group_name})", metadata.describe_group(group_name))
+            } else {
+                group_name.clone()
+            };
             init! {
                 cbox: CheckBox = (&scroll) => {
-                    text: group_name,
-                    checked: ["fitgirl-repacks.site", "FIXED"]
-                                .iter()
-                                .any(|keyword| group_name.contains(keyword))
+                    text: &text,
+                    checked: kind.is_some_and(|kind| kind.is_required()),
                 },
             }
 
             checkbox.push(cbox);
+            checkbox_groups.push(group_name.clone());
         }
 
         window.show()?;
 
-        sender.post(SelectMessage::Refresh);
+        sender.post(SelectMessage::UpdateSpace);
 
+        let target_dir =
+            std::path::absolute(&game_name).unwrap_or_else(|_| game_name.clone().into());
         Ok(Self {
             window_id: SWINDOW_ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel),
             window,
             scroll,
             checkbox,
+            checkbox_groups,
+            space,
             submit,
             groups,
+            metadata,
             game_name,
+            target_dir,
+            torrent,
         })
     }
 
@@ -109,7 +146,12 @@ impl Component for SelectWindow {
             .checkbox
             .iter_mut()
             .map(async |c| {
-                c.start(sender, |_| None, || SelectMessage::Noop).await;
+                c.start(
+                    sender,
+                    |_| Some(SelectMessage::UpdateSpace),
+                    || SelectMessage::Noop,
+                )
+                .await;
             })
             .collect::<Vec<_>>();
 
@@ -134,17 +176,42 @@ impl Component for SelectWindow {
                 Ok(false)
             }
             SelectMessage::Refresh => Ok(true),
+            SelectMessage::UpdateSpace => {
+                let (text, _) = self.describe_space(&self.selected());
+                self.space.set_text(&text)?;
+                Ok(true)
+            }
             SelectMessage::SaveFile => {
-                let ddls: Vec<_> = self
-                    .checkbox
-                    .iter()
-                    .filter(|c| c.is_checked().unwrap_or_default())
-                    .filter_map(|c| c.text().ok())
-                    .filter_map(|t| self.groups.get(&t))
-                    .flatten()
-                    .collect();
-
-                write_aria2_input(ddls, format!("{}.txt", self.game_name), &self.game_name).await;
+                let selected = self.selected();
+                let (text, enough) = self.describe_space(&selected);
+                info!("{}: {text}", self.game_name);
+                if !enough
+                    && MessageBox::new()
+                        .title(env!("CARGO_PKG_NAME"))
+                        .message("Save the links anyway?")
+                        .instruction(&text)
+                        .style(MessageBoxStyle::Warning)
+                        .buttons(MessageBoxButton::Yes | MessageBoxButton::No)
+                        .show(Some(self.window.as_window()))
+                        .await?
+                        != MessageBoxResponse::Yes
+                {
+                    return Ok(false);
+                }
+
+                let dir = self.target_dir.to_string_lossy();
+                let ddls = selected.iter().filter_map(|t| self.groups.get(t)).flatten();
+                write_aria2_input(ddls, format!("{}.txt", self.game_name), &dir).await;
+
+                if let Some((torrent_url, info)) = &self.torrent {
+                    let indices = info.select_files(selected.iter().map(String::as_str));
+                    let output = aria2_torrent_input(torrent_url, &indices, Some(&dir));
+                    let output_file = format!("{}_torrent.txt", self.game_name);
+                    match compio::fs::write(&output_file, output.into_bytes()).await.0 {
+                        Ok(_) => info!("saved: {output_file}"),
+                        Err(e) => error!("failed to save {output_file}: {e}"),
+                    }
+                }
                 Ok(false)
             }
         }
@@ -154,6 +221,7 @@ impl Component for SelectWindow {
         let mut layout_out = layout! {
             StackPanel::new(Orient::Vertical),
             self.scroll => { grow: true, margin: Margin::new_all_same(5.) },
+            self.space => { margin: Margin::new_all_same(5.) },
             self.submit => { margin: Margin::new_all_same(5.) },
         };
 
@@ -167,3 +235,38 @@ impl Component for SelectWindow {
         Ok(())
     }
 }
+
+impl SelectWindow {
+    /// Checked groups
+    fn selected(&self) -> Vec<String> {
+        self.checkbox
+            .iter()
+            .zip(&self.checkbox_groups)
+            .filter(|(c, _)| c.is_checked().unwrap_or_default())
+            .map(|(_, group)| group.clone())
+            .collect()
+    }
+
+    /// Sizes of the `selected` groups against free space of [Self::target_dir],
+    /// and whether the space is enough
+    fn describe_space(&self, selected: &[String]) -> (String, bool) {
+        let ddls = selected.iter().filter_map(|t| self.groups.get(t)).flatten();
+        let plan = SpacePlan::new(&self.metadata, ddls);
+        match plan.check(&self.target_dir) {
+            Ok((available, true)) => (format!("{plan}, {} free", format_size(available)), true),
+            Ok((available, false)) => (
+                format!(
+                    "{plan}, needs {} but only {} free in {}",
+                    format_size(plan.required()),
+                    format_size(available),
+                    self.target_dir.display()
+                ),
+                false,
+            ),
+            Err(e) => {
+                warn!("failed to get free space of {:?}: {e}", self.target_dir);
+                (format!("{plan}, free space unknown"), true)
+            }
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use std::path::Path;
+use std::sync::LazyLock;
 
 use ahash::AHashMap;
+use compio::runtime::spawn;
+use fitgirl_ddl_lib::FitgirlClient;
 use fitgirl_ddl_lib::errors::ExtractError;
-use fitgirl_ddl_lib::extract::{DDL, extract_ddl};
-use fitgirl_ddl_lib::scrape::{GameInfo, scrape_game};
+use fitgirl_ddl_lib::export::{Aria2, export_ddls};
+use fitgirl_ddl_lib::extract::DDL;
+use fitgirl_ddl_lib::pipeline::{self, EventSender, FetchEvent, FetchJob};
+use fitgirl_ddl_lib::repack::group_files;
+use fitgirl_ddl_lib::schedule::{Scheduler, SchedulerConfig};
 use futures_util::StreamExt as _;
-use itertools::Itertools as _;
 use tracing::{error, info, warn};
 use winio::prelude::{ComponentSender, Layoutable as _, Monitor, MonitorExt as _, Window};
 
@@ -16,141 +21,142 @@ pub struct ExtractionInfo {
     pub saved_files: Vec<String>,
     pub missing_files: Vec<String>,
     pub scrape_errors: Vec<String>,
+    pub incomplete_games: Vec<String>,
+}
+
+/// Client of the GUI, slower than the default since a rate limit ends the whole export
+static CLIENT: LazyLock<FitgirlClient> = LazyLock::new(|| {
+    let scheduler = Scheduler::new(SchedulerConfig {
+        rate: 0.5,
+        burst: 2,
+        max_concurrency: 2,
+        ..Default::default()
+    });
+    FitgirlClient::builder().scheduler(scheduler).build()
+});
+
+pub fn client() -> &'static FitgirlClient {
+    &CLIENT
 }
 
 pub async fn export_ddl(
     game_urls: impl Iterator<Item = impl Into<String>>,
-    mut workers: usize,
     sender: &ComponentSender<MainModel>,
     selective: bool,
 ) -> Result<ExtractionInfo, ExtractError> {
-    let scrape_results: Vec<_> =
-        futures_util::stream::iter(game_urls.map(Into::into).collect::<Vec<String>>())
-            .map(|game_url| {
-                info!("processing {game_url}");
-                async move {
-                    (
-                        game_url.clone(),
-                        scrape_game(&game_url).await.inspect_err(|e| {
-                            error!("failed to scrape {game_url}: {e}");
-                        }),
-                    )
-                }
-            })
-            .buffer_unordered(workers)
-            .collect()
-            .await;
+    let job = FetchJob::new(client()).urls(game_urls);
+    export_games(job, sender, selective).await
+}
 
-    let mut saved_files = Vec::new();
-    let mut missing_files = Vec::new();
-    let mut scrape_errors = Vec::new();
+/// Extract and save DDLs of games in `job`, with progress shown by [MainModel]
+pub async fn export_games(
+    job: FetchJob<'_>,
+    sender: &ComponentSender<MainModel>,
+    selective: bool,
+) -> Result<ExtractionInfo, ExtractError> {
+    let report = job
+        .events(progress_events(sender))
+        .fetch_torrent(true)
+        .exporter(Aria2)
+        .file_suffix("_full")
+        .subdirs(true)
+        .run()
+        .await;
 
-    let total = scrape_results
+    let mut saved_files = Vec::new();
+    let mut incomplete_games = Vec::new();
+    let missing_files = report.missing_files().map(str::to_string).collect();
+    let scrape_errors = report
+        .scrape_errors
         .iter()
-        .filter_map(|r| r.1.as_ref().ok())
-        .map(
-            |GameInfo {
-                 fuckingfast_links, ..
-             }| fuckingfast_links.len(),
-        )
-        .sum::<usize>();
-    sender.post(MainMessage::SetMaxCap(total));
-
-    for (game_url, result) in scrape_results {
-        let GameInfo {
-            path_part,
-            fuckingfast_links,
-        } = match result {
-            Ok(r) => r,
-            Err(e) => {
-                let error = format!("{game_url}: {e}");
-                scrape_errors.push(error);
-                continue;
-            }
-        };
-
-        let output_file = format!("{path_part}_full.txt");
-
-        info!("start extracting for {path_part}");
-
-        if fuckingfast_links.len() >= 100 {
-            info!("limiting workers due to too lots of DDL");
-            workers = 1;
+        .map(|(source, e)| format!("{source}: {e}"))
+        .collect();
+
+    for game_report in report.games {
+        let path_part = game_report.game.path_part;
+        if let Some(completeness) = game_report.completeness
+            && completeness.is_incomplete()
+        {
+            incomplete_games.push(format!("{path_part}:\n{}", completeness.to_string().trim()));
         }
 
-        let ddls: Vec<_> = futures_util::stream::iter(fuckingfast_links.clone())
-            .map(|ff_url| {
-                info!("processing {ff_url}");
-                async move {
-                    let result = extract_ddl(&ff_url, "").await.inspect_err(|e| {
-                        error!("failed to extract {ff_url}: {e}");
-                    });
-                    sender.post(MainMessage::IncreaseCount);
-                    result
-                }
-            })
-            .buffer_unordered(workers)
-            .collect()
-            .await;
-
-        let mut results = Vec::with_capacity(ddls.len());
-        for result in ddls {
-            match result {
-                Err(ExtractError::RateLimited) => {
-                    error!("early-exiting due to rate-limited error!");
-                    return Err(ExtractError::RateLimited);
-                }
-                Err(ExtractError::FileNotFound(filename)) => {
-                    warn!("missing file: {filename}");
-                    missing_files.push(filename);
-                }
-                Ok(result) => results.push(result),
-                _ => continue,
-            }
+        match (game_report.output, game_report.write_error) {
+            (Some(output), _) => saved_files.push(output.display().to_string()),
+            (None, Some(e)) => error!("failed to save {path_part}: {e}"),
+            (None, None) => (),
         }
 
-        write_aria2_input(&results, &output_file, &path_part).await;
-
         if selective {
-            sender.post(MainMessage::CreateSelection(results, path_part));
+            sender.post(MainMessage::CreateSelection(
+                game_report.ddls,
+                path_part,
+                game_report.torrent,
+                game_report.game.metadata,
+            ));
         }
+    }
 
-        saved_files.push(output_file);
+    if report.rate_limited {
+        error!("early-exiting due to rate-limited error!");
+        return Err(ExtractError::RateLimited);
     }
 
     Ok(ExtractionInfo {
         saved_files,
         missing_files,
         scrape_errors,
+        incomplete_games,
+    })
+}
+
+/// Sender of pipeline events, driving the progress bar of [MainModel]
+fn progress_events(sender: &ComponentSender<MainModel>) -> EventSender {
+    let (events, mut stream) = pipeline::channel();
+    let sender = sender.clone();
+    spawn(async move {
+        while let Some(event) = stream.next().await {
+            if event.is_link_done() {
+                sender.post(MainMessage::IncreaseCount);
+            }
+            match event {
+                FetchEvent::ScrapeStarted { url } => info!("processing {url}"),
+                FetchEvent::ScrapeFailed { url, error } => {
+                    error!("failed to scrape {url}: {error}")
+                }
+                FetchEvent::ExtractPlanned { links, .. } => {
+                    sender.post(MainMessage::SetMaxCap(links))
+                }
+                FetchEvent::PartsChecked { path_part, report } if report.is_incomplete() => {
+                    warn!("incomplete parts for {path_part}:\n{report}")
+                }
+                FetchEvent::ExtractStarted { path_part, .. } => {
+                    info!("start extracting for {path_part}")
+                }
+                FetchEvent::LinkFailed { url, error } => {
+                    error!("failed to extract {url}: {error}")
+                }
+                FetchEvent::FileMissing { filename, .. } => warn!("missing file: {filename}"),
+                FetchEvent::GameWritten { path, .. } => info!("saved: {path:?}"),
+                _ => (),
+            }
+        }
     })
+    .detach();
+    events
 }
 
-#[rustfmt::skip]
-pub async fn write_aria2_input(
-    ddls: impl IntoIterator<Item = &DDL>,
+/// Save aria2 input of `ddls`, downloaded into `dir`
+pub async fn write_aria2_input<'a>(
+    ddls: impl IntoIterator<Item = &'a DDL>,
     output_file: impl AsRef<Path>,
-    path_part: &str,
+    dir: &str,
 ) {
-    let output_string: String = ddls
-        .into_iter()
-        .sorted_by(|&a, &b|{
-            a.filename.cmp(&b.filename)
-        })
-        .map(
-            |DDL {
-                filename,
-                direct_link,
-            }| {
-                format!(
-"{direct_link}
-    out={path_part}/{filename}
-    continue=true
-"
-                )
-            },
-        ).collect();
-
-    match compio::fs::write(&output_file, output_string.into_bytes()).await.0 {
+    let output_string = export_ddls(&Aria2, ddls, Some(dir));
+
+    match compio::fs::write(&output_file, output_string.into_bytes())
+        .await
+        .0
+    {
         Ok(_) => {
             info!("saved: {:?}", output_file.as_ref());
         }
@@ -161,24 +167,7 @@ pub async fn write_aria2_input(
 }
 
 pub fn collect_groups(ddls: impl IntoIterator<Item = DDL>) -> AHashMap<String, Vec<DDL>> {
-    let mut groups: AHashMap<String, Vec<DDL>> = AHashMap::new();
-
-    for DDL {
-        filename,
-        direct_link,
-    } in ddls
-    {
-        let group_name = filename
-            .split_once(".part")
-            .map(|(pre, _)| pre.to_string())
-            .unwrap_or(filename.clone());
-        groups.entry(group_name).or_default().push(DDL {
-            filename,
-            direct_link,
-        });
-    }
-
-    groups
+    group_files(ddls, |ddl| &ddl.filename).into_iter().collect()
 }
 
 pub fn centralize_window(window: &mut Window) -> winio::Result<()> {
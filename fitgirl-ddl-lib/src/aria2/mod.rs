@@ -0,0 +1,381 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use futures_util::lock::Mutex;
+use http::header::CONTENT_TYPE;
+use serde_json::{Map, Value, json};
+use tracing::{debug, warn};
+use wreq::Client;
+use wreq::ws::WebSocket;
+use wreq::ws::message::Message;
+
+use crate::errors::Aria2Error;
+use crate::extract::DDL;
+use crate::runtime;
+
+/// Client of a running aria2c with `--enable-rpc`.
+///
+/// `http(s)://` endpoints send one request per call,
+/// `ws(s)://` endpoints keep a WebSocket open and reconnect when it breaks.
+pub struct Aria2Client {
+    endpoint: String,
+    secret: Option<String>,
+    http: Client,
+    ws: Mutex<Option<WebSocket>>,
+    next_id: AtomicU64,
+}
+
+/// Progress of a download, from `aria2.tellStatus`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aria2Status {
+    pub gid: String,
+    /// `active`, `waiting`, `paused`, `error`, `complete` or `removed`
+    pub status: String,
+    pub total_length: u64,
+    pub completed_length: u64,
+    /// Bytes per second
+    pub download_speed: u64,
+    pub error_message: Option<String>,
+}
+
+impl Aria2Status {
+    /// Whether aria2 is done with it, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "complete" | "error" | "removed")
+    }
+}
+
+impl Aria2Client {
+    /// `endpoint` is like `http://localhost:6800/jsonrpc`, `secret` is `--rpc-secret` of aria2c.
+    pub fn new(endpoint: impl Into<String>, secret: Option<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            secret,
+            http: Client::new(),
+            ws: Mutex::new(None),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Call an RPC method, the secret token is prepended to `params`.
+    ///
+    /// Fails with [Aria2Error::NoResponse] if the request may have reached aria2,
+    /// which is not retried as methods like `aria2.addUri` would run twice.
+    pub async fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, Aria2Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let params = self
+            .secret
+            .iter()
+            .map(|secret| Value::String(format!("token:{secret}")))
+            .chain(params)
+            .collect::<Vec<_>>();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+
+        let response = if self.endpoint.starts_with("ws") {
+            self.send_ws(&id, request).await?
+        } else {
+            self.send_http(request).await?
+        };
+        parse_response(response)
+    }
+
+    /// Queue `uris` of one file, returns its GID.
+    ///
+    /// `options` are aria2 input file options like `dir` and `out`.
+    pub async fn add_uri(
+        &self,
+        uris: &[&str],
+        options: Map<String, Value>,
+    ) -> Result<String, Aria2Error> {
+        let gid = self
+            .call("aria2.addUri", vec![json!(uris), Value::Object(options)])
+            .await?;
+        gid.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Aria2Error::InvalidResponse(gid.to_string()))
+    }
+
+    /// Queue `ddl` as `dir/filename`, continuing partial files, returns its GID.
+    pub async fn add_ddl(&self, ddl: &DDL, dir: Option<&str>) -> Result<String, Aria2Error> {
+        let mut options = Map::new();
+        options.insert("out".to_string(), json!(ddl.filename));
+        options.insert("continue".to_string(), json!("true"));
+        if let Some(dir) = dir {
+            options.insert("dir".to_string(), json!(dir));
+        }
+        self.add_uri(&[&ddl.direct_link], options).await
+    }
+
+    pub async fn tell_status(&self, gid: &str) -> Result<Aria2Status, Aria2Error> {
+        let keys = [
+            "gid",
+            "status",
+            "totalLength",
+            "completedLength",
+            "downloadSpeed",
+            "errorMessage",
+        ];
+        let status = self
+            .call("aria2.tellStatus", vec![json!(gid), json!(keys)])
+            .await?;
+
+        let field = |key: &str| status.get(key).and_then(Value::as_str);
+        // aria2 sends numbers as strings
+        let number = |key: &str| field(key).and_then(|n| n.parse().ok()).unwrap_or_default();
+        Ok(Aria2Status {
+            gid: field("gid").unwrap_or(gid).to_string(),
+            status: field("status")
+                .ok_or_else(|| Aria2Error::InvalidResponse(status.to_string()))?
+                .to_string(),
+            total_length: number("totalLength"),
+            completed_length: number("completedLength"),
+            download_speed: number("downloadSpeed"),
+            error_message: field("errorMessage")
+                .filter(|message| !message.is_empty())
+                .map(str::to_string),
+        })
+    }
+
+    /// Poll `gids` every `interval` until all are finished, reporting each round to `on_progress`.
+    pub async fn watch(
+        &self,
+        gids: &[String],
+        interval: Duration,
+        mut on_progress: impl FnMut(&[Aria2Status]),
+    ) -> Result<Vec<Aria2Status>, Aria2Error> {
+        loop {
+            let mut statuses = Vec::with_capacity(gids.len());
+            for gid in gids {
+                statuses.push(self.tell_status(gid).await?);
+            }
+            on_progress(&statuses);
+
+            if statuses.iter().all(Aria2Status::is_finished) {
+                return Ok(statuses);
+            }
+            runtime::sleep(interval).await;
+        }
+    }
+
+    async fn send_http(&self, request: String) -> Result<Value, Aria2Error> {
+        let text = self
+            .http
+            .post(self.endpoint.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(request)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_connect() {
+                    Aria2Error::RequestError(e.to_string())
+                } else {
+                    Aria2Error::NoResponse(e.to_string())
+                }
+            })?
+            .text()
+            .await
+            .map_err(|e| Aria2Error::NoResponse(e.to_string()))?;
+        serde_json::from_str(&text).map_err(|_| Aria2Error::InvalidResponse(text))
+    }
+
+    async fn send_ws(&self, id: &str, request: String) -> Result<Value, Aria2Error> {
+        let mut guard = self.ws.lock().await;
+        let mut ws = match guard.take() {
+            Some(ws) => ws,
+            None => self.connect_ws().await?,
+        };
+        // dropped on errors, so the next call reconnects
+        let response = exchange_ws(&mut ws, id, request).await?;
+        *guard = Some(ws);
+        Ok(response)
+    }
+
+    async fn connect_ws(&self) -> Result<WebSocket, Aria2Error> {
+        debug!("connecting to {}", self.endpoint);
+        self.http
+            .websocket(self.endpoint.as_str())
+            .send()
+            .await
+            .map_err(|e| Aria2Error::RequestError(e.to_string()))?
+            .into_websocket()
+            .await
+            .map_err(|e| Aria2Error::RequestError(e.to_string()))
+    }
+}
+
+async fn exchange_ws(ws: &mut WebSocket, id: &str, request: String) -> Result<Value, Aria2Error> {
+    ws.send(Message::text(request))
+        .await
+        .map_err(|e| Aria2Error::RequestError(e.to_string()))?;
+
+    // skip notifications like `aria2.onDownloadStart`
+    while let Some(message) = ws.recv().await {
+        let message = message.map_err(|e| Aria2Error::NoResponse(e.to_string()))?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+        match serde_json::from_str::<Value>(text.as_str()) {
+            Ok(response) if response.get("id").and_then(Value::as_str) == Some(id) => {
+                return Ok(response);
+            }
+            Ok(_) => debug!("skipping aria2 message: {}", text.as_str()),
+            Err(e) => warn!("invalid aria2 message: {e}"),
+        }
+    }
+    Err(Aria2Error::NoResponse("websocket closed".to_string()))
+}
+
+fn parse_response(mut response: Value) -> Result<Value, Aria2Error> {
+    if let Some(error) = response.get("error") {
+        return Err(Aria2Error::Rpc {
+            code: error
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    match response.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(Aria2Error::InvalidResponse(response.to_string())),
+    }
+}
+
+#[cfg(all(test, feature = "compio"))]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::testing::{closed_endpoint, ddl, serve};
+
+    const GID: &str = "2089b05ecca3d829";
+
+    /// Answers like aria2c, unknown methods fail like a wrong secret does
+    fn answer(body: &str) -> Option<String> {
+        let call: Value = serde_json::from_str(body).unwrap();
+        let result = match call["method"].as_str() {
+            Some("aria2.addUri") => json!(GID),
+            Some("aria2.tellStatus") => json!({
+                "gid": GID,
+                "status": "error",
+                "totalLength": "100",
+                "completedLength": "40",
+                "downloadSpeed": "0",
+                "errorMessage": "Resource not found",
+            }),
+            _ => {
+                let error = json!({ "code": 1, "message": "Unauthorized" });
+                return Some(
+                    json!({ "jsonrpc": "2.0", "id": call["id"], "error": error }).to_string(),
+                );
+            }
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }).to_string())
+    }
+
+    #[compio::test]
+    async fn sends_token_and_parses_responses() {
+        let (base, requests) = serve(|request| answer(&request.body));
+        let aria2 = Aria2Client::new(format!("{base}/jsonrpc"), Some("s3cret".to_string()));
+
+        let gid = aria2
+            .add_ddl(&ddl("a.rar"), Some("/games/Game"))
+            .await
+            .unwrap();
+        assert_eq!(gid, GID);
+        let request = requests.recv().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/jsonrpc")
+        );
+        let call: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(call["jsonrpc"], "2.0");
+        assert_eq!(call["method"], "aria2.addUri");
+        assert_eq!(
+            call["params"],
+            json!([
+                "token:s3cret",
+                ["https://dl.example/a.rar"],
+                { "out": "a.rar", "continue": "true", "dir": "/games/Game" },
+            ])
+        );
+
+        let status = aria2.tell_status(&gid).await.unwrap();
+        assert_eq!(
+            status,
+            Aria2Status {
+                gid: GID.to_string(),
+                status: "error".to_string(),
+                total_length: 100,
+                completed_length: 40,
+                download_speed: 0,
+                error_message: Some("Resource not found".to_string()),
+            }
+        );
+        assert!(status.is_finished());
+        let call: Value = serde_json::from_str(&requests.recv().unwrap().body).unwrap();
+        assert_eq!(call["method"], "aria2.tellStatus");
+        assert_eq!(call["params"][0], "token:s3cret");
+        assert_eq!(call["params"][1], GID);
+
+        let result = aria2.call("aria2.unknown", Vec::new()).await;
+        assert!(
+            matches!(&result, Err(Aria2Error::Rpc { code: 1, message }) if message == "Unauthorized"),
+            "{result:?}"
+        );
+    }
+
+    #[compio::test]
+    async fn omits_token_without_secret() {
+        let (base, requests) = serve(|request| answer(&request.body));
+        let aria2 = Aria2Client::new(format!("{base}/jsonrpc"), None);
+
+        aria2.add_ddl(&ddl("a.rar"), None).await.unwrap();
+        let call: Value = serde_json::from_str(&requests.recv().unwrap().body).unwrap();
+        assert_eq!(
+            call["params"],
+            json!([["https://dl.example/a.rar"], { "out": "a.rar", "continue": "true" }])
+        );
+    }
+
+    #[compio::test]
+    async fn does_not_retry_unanswered_calls() {
+        let retry = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..Default::default()
+        };
+
+        // aria2 may have queued it, sending it again would download twice
+        let (base, requests) = serve(|_| None::<String>);
+        let aria2 = Aria2Client::new(format!("{base}/jsonrpc"), None);
+        let result = retry
+            .run(async || aria2.add_ddl(&ddl("a.rar"), None).await)
+            .await;
+        assert!(
+            matches!(result, Err(Aria2Error::NoResponse(_))),
+            "{result:?}"
+        );
+        assert!(requests.recv().is_ok());
+        assert!(requests.try_recv().is_err());
+
+        // nothing was sent, trying again is safe
+        let aria2 = Aria2Client::new(closed_endpoint(), None);
+        let result = retry
+            .run(async || aria2.add_ddl(&ddl("a.rar"), None).await)
+            .await;
+        assert!(
+            matches!(result, Err(Aria2Error::RequestError(_))),
+            "{result:?}"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use crate::torrent::TorrentInfo;
+
+/// Differences between fuckingfast parts and the torrent file list
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletenessReport {
+    /// In the torrent, but not linked on fuckingfast
+    pub missing: Vec<String>,
+    /// Linked on fuckingfast, but not in the torrent
+    pub extra: Vec<String>,
+    /// Linked more than once on fuckingfast
+    pub duplicated: Vec<String>,
+    /// `(fuckingfast, torrent)` filenames which only differ in case or separators
+    pub mismatched: Vec<(String, String)>,
+    /// No part is a file of the torrent, e.g. `.rar` volumes packing its `.bin` files,
+    /// so missing and extra files are unknown.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub incomparable: bool,
+}
+
+impl CompletenessReport {
+    /// Every file of the torrent is linked once, and nothing else
+    pub fn is_complete(&self) -> bool {
+        !self.incomparable
+            && self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.duplicated.is_empty()
+            && self.mismatched.is_empty()
+    }
+
+    /// Parts are known to differ from the torrent, [Self::incomparable] ones only by duplicates
+    pub fn is_incomplete(&self) -> bool {
+        !self.duplicated.is_empty() || (!self.incomparable && !self.is_complete())
+    }
+}
+
+impl Display for CompletenessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_complete() {
+            return write!(f, "all parts present");
+        }
+        if self.incomparable {
+            writeln!(f, "parts are not files of the torrent, can't compare them")?;
+        }
+        for name in &self.missing {
+            writeln!(f, "missing: {name}")?;
+        }
+        for name in &self.extra {
+            writeln!(f, "extra: {name}")?;
+        }
+        for name in &self.duplicated {
+            writeln!(f, "duplicated: {name}")?;
+        }
+        for (fuckingfast, torrent) in &self.mismatched {
+            writeln!(f, "mismatched: {fuckingfast} (torrent: {torrent})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare filenames in fragments of `fuckingfast_links` with files of `torrent`.
+///
+/// The report is [CompletenessReport::incomparable] if no part matches a file of the torrent.
+pub fn check_completeness(
+    fuckingfast_links: &[String],
+    torrent: &TorrentInfo,
+) -> CompletenessReport {
+    let mut report = CompletenessReport::default();
+
+    let mut linked: BTreeMap<&str, usize> = BTreeMap::new();
+    for filename in fuckingfast_links
+        .iter()
+        .filter_map(|link| link.split('#').nth(1))
+    {
+        *linked.entry(filename).or_default() += 1;
+    }
+    report.duplicated = linked
+        .iter()
+        .filter(|&(_, &count)| count > 1)
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    let mut unmatched: Vec<&str> = torrent
+        .files
+        .iter()
+        .map(|file| file.filename())
+        .filter(|name| !linked.contains_key(name))
+        .collect();
+
+    for &name in linked.keys() {
+        if torrent.files.iter().any(|file| file.filename() == name) {
+            continue;
+        }
+
+        let normalized = normalize(name);
+        match unmatched
+            .iter()
+            .position(|other| normalize(other) == normalized)
+        {
+            Some(i) => {
+                let other = unmatched.remove(i);
+                report
+                    .mismatched
+                    .push((name.to_string(), other.to_string()));
+            }
+            None => report.extra.push(name.to_string()),
+        }
+    }
+    report.missing = unmatched.into_iter().map(str::to_string).collect();
+
+    // archives of another layout, everything would be both missing and extra
+    let matched = linked.len() - report.extra.len();
+    if matched == 0 && !linked.is_empty() && !torrent.files.is_empty() {
+        return CompletenessReport {
+            duplicated: report.duplicated,
+            incomparable: true,
+            ..Default::default()
+        };
+    }
+
+    report
+}
+
+fn normalize(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::torrent::TorrentFile;
+
+    fn torrent(paths: &[&str]) -> TorrentInfo {
+        TorrentInfo {
+            name: "Game".to_string(),
+            files: paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| TorrentFile {
+                    index: i + 1,
+                    path: path.to_string(),
+                    length: 1,
+                })
+                .collect(),
+        }
+    }
+
+    fn links(filenames: &[&str]) -> Vec<String> {
+        filenames
+            .iter()
+            .map(|filename| format!("https://fuckingfast.co/abc#{filename}"))
+            .collect()
+    }
+
+    #[test]
+    fn matching_parts_are_complete() {
+        let report = check_completeness(
+            &links(&["setup.exe", "fg-01.bin", "fitgirl-bins.md5"]),
+            &torrent(&["setup.exe", "fg-01.bin", "MD5/fitgirl-bins.md5"]),
+        );
+        assert!(report.is_complete());
+        assert!(!report.is_incomplete());
+        assert_eq!(report.to_string(), "all parts present");
+    }
+
+    #[test]
+    fn reports_missing_extra_and_duplicated_parts() {
+        let report = check_completeness(
+            &links(&["setup.exe", "fg-01.bin", "fg-01.bin", "fg-03.bin"]),
+            &torrent(&["setup.exe", "fg-01.bin", "fg-02.bin"]),
+        );
+        assert_eq!(report.missing, ["fg-02.bin"]);
+        assert_eq!(report.extra, ["fg-03.bin"]);
+        assert_eq!(report.duplicated, ["fg-01.bin"]);
+        assert!(report.mismatched.is_empty());
+        assert!(report.is_incomplete());
+        assert_eq!(
+            report.to_string(),
+            "missing: fg-02.bin\nextra: fg-03.bin\nduplicated: fg-01.bin\n"
+        );
+    }
+
+    #[test]
+    fn pairs_names_differing_in_case_or_separators() {
+        let report = check_completeness(
+            &links(&["setup.exe", "FG-Selective-English.bin"]),
+            &torrent(&["setup.exe", "fg_selective_english.bin"]),
+        );
+        assert_eq!(
+            report.mismatched,
+            [(
+                "FG-Selective-English.bin".to_string(),
+                "fg_selective_english.bin".to_string()
+            )]
+        );
+        assert!(report.missing.is_empty() && report.extra.is_empty());
+        assert!(report.is_incomplete());
+    }
+
+    #[test]
+    fn archives_of_torrent_files_are_incomparable() {
+        let report = check_completeness(
+            &links(&[
+                "Game_--_fitgirl-repacks.site_--_.part1.rar",
+                "Game_--_fitgirl-repacks.site_--_.part2.rar",
+            ]),
+            &torrent(&["setup.exe", "fg-01.bin", "fg-02.bin"]),
+        );
+        assert!(report.incomparable);
+        assert!(report.missing.is_empty() && report.extra.is_empty());
+        assert!(!report.is_complete());
+        assert!(!report.is_incomplete());
+
+        let report = check_completeness(
+            &links(&["game.part1.rar", "game.part1.rar"]),
+            &torrent(&["fg-01.bin"]),
+        );
+        assert!(report.incomparable);
+        assert!(report.is_incomplete());
+    }
+}
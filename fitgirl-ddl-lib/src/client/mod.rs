@@ -0,0 +1,185 @@
+use std::sync::{Arc, RwLock};
+
+use http::HeaderValue;
+use url::Url;
+use wreq::Client;
+
+use crate::HTTP_CLIENT;
+use crate::hoster::{FuckingFast, Hoster};
+use crate::schedule::Scheduler;
+
+pub const FITGIRL_BASE_URL: &str = "https://fitgirl-repacks.site/";
+pub const FUCKINGFAST_BASE_URL: &str = "https://fuckingfast.co/";
+
+/// Owns the HTTP client, per-site cookies and base URLs used by the scraper and extractor.
+///
+/// Cloning is cheap, clones share the same cookies.
+#[derive(Clone)]
+pub struct FitgirlClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    http: Client,
+    fitgirl_base: Url,
+    fuckingfast_base: Url,
+    fitgirl_cookies: RwLock<Option<HeaderValue>>,
+    fuckingfast_cookies: RwLock<Option<HeaderValue>>,
+    hosters: Vec<Arc<dyn Hoster>>,
+    scheduler: Scheduler,
+}
+
+pub struct FitgirlClientBuilder {
+    http: Option<Client>,
+    fitgirl_base: Url,
+    fuckingfast_base: Url,
+    fitgirl_cookies: Option<HeaderValue>,
+    fuckingfast_cookies: Option<HeaderValue>,
+    hosters: Vec<Arc<dyn Hoster>>,
+    scheduler: Option<Scheduler>,
+}
+
+impl Default for FitgirlClientBuilder {
+    fn default() -> Self {
+        Self {
+            http: None,
+            fitgirl_base: Url::parse(FITGIRL_BASE_URL).unwrap(),
+            fuckingfast_base: Url::parse(FUCKINGFAST_BASE_URL).unwrap(),
+            fitgirl_cookies: None,
+            fuckingfast_cookies: None,
+            hosters: Vec::new(),
+            scheduler: None,
+        }
+    }
+}
+
+impl FitgirlClientBuilder {
+    /// Use a custom wreq client, defaults to [HTTP_CLIENT].
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http = Some(client);
+        self
+    }
+
+    pub fn fitgirl_base(mut self, base: Url) -> Self {
+        self.fitgirl_base = base;
+        self
+    }
+
+    pub fn fuckingfast_base(mut self, base: Url) -> Self {
+        self.fuckingfast_base = base;
+        self
+    }
+
+    /// Cookies sent to fitgirl-repacks.site, in form like `name1=value1; name=value2; ...`
+    pub fn fitgirl_cookies(mut self, cookies: impl Into<HeaderValue>) -> Self {
+        self.fitgirl_cookies = Some(cookies.into());
+        self
+    }
+
+    /// Cookies sent to fuckingfast.co, for example `cf_clearance=...; dlpass=...`
+    pub fn fuckingfast_cookies(mut self, cookies: impl Into<HeaderValue>) -> Self {
+        self.fuckingfast_cookies = Some(cookies.into());
+        self
+    }
+
+    /// Register an extra hoster, [FuckingFast] is always registered first.
+    pub fn hoster(mut self, hoster: impl Hoster + 'static) -> Self {
+        self.hosters.push(Arc::new(hoster));
+        self
+    }
+
+    /// Share a scheduler with other clients, defaults to a new one with [SchedulerConfig](crate::schedule::SchedulerConfig) defaults.
+    pub fn scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    pub fn build(self) -> FitgirlClient {
+        let fuckingfast: Arc<dyn Hoster> = Arc::new(
+            self.fuckingfast_base
+                .host_str()
+                .map(FuckingFast::with_domain)
+                .unwrap_or_default(),
+        );
+        let hosters = std::iter::once(fuckingfast).chain(self.hosters).collect();
+
+        FitgirlClient {
+            inner: Arc::new(Inner {
+                http: self.http.unwrap_or_else(|| HTTP_CLIENT.clone()),
+                fitgirl_base: self.fitgirl_base,
+                fuckingfast_base: self.fuckingfast_base,
+                fitgirl_cookies: RwLock::new(self.fitgirl_cookies),
+                fuckingfast_cookies: RwLock::new(self.fuckingfast_cookies),
+                hosters,
+                scheduler: self.scheduler.unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+impl Default for FitgirlClient {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl FitgirlClient {
+    pub fn builder() -> FitgirlClientBuilder {
+        FitgirlClientBuilder::default()
+    }
+
+    pub fn http(&self) -> &Client {
+        &self.inner.http
+    }
+
+    pub fn fitgirl_base(&self) -> &Url {
+        &self.inner.fitgirl_base
+    }
+
+    pub fn fuckingfast_base(&self) -> &Url {
+        &self.inner.fuckingfast_base
+    }
+
+    pub fn hosters(&self) -> &[Arc<dyn Hoster>] {
+        &self.inner.hosters
+    }
+
+    /// Rate limiting of extractions, shared by clones of this client
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.inner.scheduler
+    }
+
+    pub fn fitgirl_cookies(&self) -> Option<HeaderValue> {
+        self.inner
+            .fitgirl_cookies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn fuckingfast_cookies(&self) -> Option<HeaderValue> {
+        self.inner
+            .fuckingfast_cookies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replace cookies for fitgirl-repacks.site, e.g. after the old ones expired.
+    pub fn set_fitgirl_cookies(&self, cookies: impl Into<HeaderValue>) {
+        *self
+            .inner
+            .fitgirl_cookies
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(cookies.into());
+    }
+
+    /// Replace cookies for fuckingfast.co.
+    pub fn set_fuckingfast_cookies(&self, cookies: impl Into<HeaderValue>) {
+        *self
+            .inner
+            .fuckingfast_cookies
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(cookies.into());
+    }
+}
@@ -0,0 +1,618 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt as _;
+use futures_util::future::try_join_all;
+use http::StatusCode;
+use http::header::{CONTENT_TYPE, RANGE};
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "compio")]
+use compio::runtime::spawn_blocking;
+#[cfg(feature = "tokio")]
+use tokio::task::spawn_blocking;
+
+use crate::errors::DownloadError;
+use crate::extract::DDL;
+use crate::probe::looks_expired;
+use crate::{FitgirlClient, default_client};
+
+mod state;
+
+pub use state::{DownloadState, Segment, UNKNOWN_END};
+
+/// Suffix of the file being written
+pub const PARTIAL_SUFFIX: &str = ".part";
+/// Suffix of the [DownloadState] file next to the partial file
+pub const STATE_SUFFIX: &str = ".fgdl";
+/// Suffix of a [DownloadState] being saved, renamed over the state file once written
+const STATE_TEMP_SUFFIX: &str = ".tmp";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadOptions {
+    /// Concurrent range requests per file
+    pub segments: usize,
+    /// Files smaller than `segments * min_segment_size` use fewer segments
+    pub min_segment_size: u64,
+    /// Bytes buffered in memory before writing to disk
+    pub buffer_size: usize,
+    /// Bytes written between saves of the state file
+    pub save_interval: u64,
+    /// Times to re-extract a direct link which expired, see [FitgirlClient::refresh_ddl]
+    pub max_refreshes: u32,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            segments: 4,
+            min_segment_size: 8 << 20,
+            buffer_size: 1 << 20,
+            save_interval: 16 << 20,
+            max_refreshes: 3,
+        }
+    }
+}
+
+/// Download with [default_client].
+pub async fn download(
+    ddl: &DDL,
+    dir: impl AsRef<Path>,
+    options: &DownloadOptions,
+) -> Result<PathBuf, DownloadError> {
+    default_client().download(ddl, dir, options).await
+}
+
+/// A partial download shared by its segments
+struct Transfer {
+    file: Arc<File>,
+    state: Arc<Mutex<DownloadState>>,
+    state_path: PathBuf,
+    /// Held while saving, so an older snapshot never replaces a newer one
+    saving: Arc<Mutex<()>>,
+}
+
+impl Transfer {
+    fn snapshot(&self) -> DownloadState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Persist the progress, only after the data it records is on disk.
+    ///
+    /// The state is written to a temporary file and renamed over the old one,
+    /// so a crash leaves either the old or the new state.
+    async fn save(&self) -> Result<(), DownloadError> {
+        let file = self.file.clone();
+        let state = self.state.clone();
+        let state_path = self.state_path.clone();
+        let saving = self.saving.clone();
+        blocking(move || {
+            let _saving = saving.lock().unwrap_or_else(|e| e.into_inner());
+            let state = state.lock().unwrap_or_else(|e| e.into_inner()).to_string();
+            file.sync_data()?;
+
+            let temp_path = with_suffix(&state_path, STATE_TEMP_SUFFIX);
+            let mut temp = File::create(&temp_path)?;
+            temp.write_all(state.as_bytes())?;
+            temp.sync_data()?;
+            drop(temp);
+            std::fs::rename(temp_path, state_path)
+        })
+        .await
+    }
+}
+
+impl FitgirlClient {
+    /// Download `ddl` into `dir/filename`, resuming from the state file left by a previous run.
+    ///
+    /// Data is written to `filename.part` first, and renamed once complete.
+    /// Expired direct links are refreshed from [DDL::source_url], keeping the progress.
+    pub async fn download(
+        &self,
+        ddl: &DDL,
+        dir: impl AsRef<Path>,
+        options: &DownloadOptions,
+    ) -> Result<PathBuf, DownloadError> {
+        let path = dir.as_ref().join(&ddl.filename);
+        let partial_path = with_suffix(&path, PARTIAL_SUFFIX);
+        let state_path = with_suffix(&path, STATE_SUFFIX);
+
+        let (finished, saved) = {
+            let path = path.clone();
+            let partial_path = partial_path.clone();
+            let state_path = state_path.clone();
+            blocking(move || {
+                let saved = match std::fs::read_to_string(&state_path) {
+                    // progress is useless without the partial file
+                    Ok(text) if partial_path.exists() => Some(text),
+                    Ok(_) => None,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(e),
+                };
+                Ok((path.exists() && saved.is_none(), saved))
+            })
+            .await?
+        };
+        if finished {
+            info!("{} already downloaded", ddl.filename);
+            return Ok(path);
+        }
+
+        let mut refreshes = 0;
+        let mut direct_link = ddl.direct_link.clone();
+        if ddl.is_expired() && options.max_refreshes > 0 {
+            info!("{} expired, refreshing", ddl.filename);
+            direct_link = self.refresh_ddl(ddl).await?.direct_link;
+            refreshes += 1;
+        }
+
+        let mut probe = match &ddl.probe {
+            Some(probe) if refreshes == 0 => probe.clone(),
+            _ => self.probe(&direct_link).await?,
+        };
+        if probe.looks_expired() && refreshes < options.max_refreshes {
+            info!("{} stopped working, refreshing", ddl.filename);
+            direct_link = self.refresh_ddl(ddl).await?.direct_link;
+            refreshes += 1;
+            probe = self.probe(&direct_link).await?;
+        }
+        if !probe.is_alive() {
+            return Err(DownloadError::UnexpectedStatus(probe.status));
+        }
+        let segments = if probe.accept_ranges {
+            options.segments
+        } else {
+            1
+        };
+
+        let resumed = saved
+            .and_then(|text| {
+                text.parse::<DownloadState>()
+                    .inspect_err(|e| warn!("ignoring state of {}: {e}", ddl.filename))
+                    .ok()
+            })
+            .filter(|state| {
+                if !probe.accept_ranges {
+                    warn!("{} cannot be resumed, restarting", ddl.filename);
+                    false
+                } else if state.total != probe.content_length {
+                    warn!("{} changed on server, restarting", ddl.filename);
+                    false
+                } else {
+                    true
+                }
+            });
+        let state = match resumed {
+            Some(state) => {
+                info!(
+                    "resuming {} from {} bytes",
+                    ddl.filename,
+                    state.downloaded()
+                );
+                state
+            }
+            None => DownloadState::plan(probe.content_length, segments, options.min_segment_size),
+        };
+
+        let file = {
+            let partial_path = partial_path.clone();
+            let fresh = state.downloaded() == 0;
+            let total = state.total;
+            blocking(move || {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(false)
+                    .open(partial_path)?;
+                if fresh {
+                    file.set_len(total.unwrap_or_default())?;
+                }
+                Ok(file)
+            })
+            .await?
+        };
+
+        let transfer = Transfer {
+            file: Arc::new(file),
+            state: Arc::new(Mutex::new(state)),
+            state_path,
+            saving: Arc::default(),
+        };
+        transfer.save().await?;
+
+        loop {
+            let pending: Vec<usize> = transfer
+                .snapshot()
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(_, segment)| !segment.is_complete())
+                .map(|(i, _)| i)
+                .collect();
+            let result = try_join_all(
+                pending
+                    .into_iter()
+                    .map(|i| self.download_segment(&direct_link, &transfer, i, options)),
+            )
+            .await;
+            transfer.save().await?;
+
+            match result {
+                Ok(_) => break,
+                Err(DownloadError::LinkExpired(status)) if refreshes < options.max_refreshes => {
+                    warn!(
+                        "{} stopped working with status {status} at {} bytes, refreshing",
+                        ddl.filename,
+                        transfer.snapshot().downloaded()
+                    );
+                    direct_link = self.refresh_ddl(ddl).await?.direct_link;
+                    refreshes += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let Transfer {
+            file, state_path, ..
+        } = transfer;
+        {
+            let path = path.clone();
+            blocking(move || {
+                file.sync_all()?;
+                drop(file);
+                std::fs::rename(partial_path, path)?;
+                std::fs::remove_file(state_path)
+            })
+            .await?;
+        }
+
+        info!("downloaded {}", ddl.filename);
+        Ok(path)
+    }
+
+    async fn download_segment(
+        &self,
+        direct_link: &str,
+        transfer: &Transfer,
+        index: usize,
+        options: &DownloadOptions,
+    ) -> Result<(), DownloadError> {
+        let segment = transfer.snapshot().segments[index];
+        let mut position = segment.position();
+        debug!("segment {index} of {direct_link} from {position}");
+
+        let mut request = self.http().get(direct_link);
+        if segment.end != UNKNOWN_END {
+            request = request.header(RANGE, format!("bytes={position}-{}", segment.end - 1));
+        } else if position > 0 {
+            request = request.header(RANGE, format!("bytes={position}-"));
+        }
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| DownloadError::RequestError(e.to_string()))?;
+
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if looks_expired(resp.status().as_u16(), content_type) {
+            return Err(DownloadError::LinkExpired(resp.status().as_u16()));
+        }
+
+        match resp.status() {
+            StatusCode::PARTIAL_CONTENT => (),
+            StatusCode::OK if position == 0 => (),
+            StatusCode::OK => return Err(DownloadError::RangeIgnored),
+            status => return Err(DownloadError::UnexpectedStatus(status.as_u16())),
+        }
+
+        let mut stream = resp.bytes_stream();
+        let mut buffer = Vec::with_capacity(options.buffer_size);
+        let mut unsaved = 0;
+        while position + (buffer.len() as u64) < segment.end {
+            let Some(chunk) = stream.next().await else {
+                break;
+            };
+            let chunk = chunk.map_err(|e| DownloadError::RequestError(e.to_string()))?;
+
+            // servers ignoring the end of range may send more than asked
+            let wanted = segment.end - position - buffer.len() as u64;
+            let chunk = &chunk[..chunk.len().min(wanted.try_into().unwrap_or(usize::MAX))];
+            buffer.extend_from_slice(chunk);
+
+            if buffer.len() >= options.buffer_size {
+                let written = write_segment(transfer, index, position, &mut buffer).await?;
+                position += written;
+                unsaved += written;
+                if unsaved >= options.save_interval {
+                    transfer.save().await?;
+                    unsaved = 0;
+                }
+            }
+        }
+        position += write_segment(transfer, index, position, &mut buffer).await?;
+
+        if segment.end == UNKNOWN_END {
+            let mut state = transfer.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.segments[index].end = position;
+            state.total = Some(position);
+        } else if position < segment.end {
+            return Err(DownloadError::Incomplete {
+                received: position - segment.start,
+                expected: segment.end - segment.start,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Write out `buffer` at `position` and record it in the state, returns bytes written
+async fn write_segment(
+    transfer: &Transfer,
+    index: usize,
+    position: u64,
+    buffer: &mut Vec<u8>,
+) -> Result<u64, DownloadError> {
+    if buffer.is_empty() {
+        return Ok(0);
+    }
+
+    let data = std::mem::take(buffer);
+    let len = data.len() as u64;
+    let file = transfer.file.clone();
+    let data = blocking(move || write_all_at(&file, &data, position).map(|_| data)).await?;
+
+    // reuse the allocation
+    *buffer = data;
+    buffer.clear();
+
+    transfer
+        .state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .segments[index]
+        .downloaded += len;
+    Ok(len)
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> Result<T, DownloadError> {
+    Ok(spawn_blocking(f)
+        .await
+        .map_err(|_| DownloadError::JoinError)??)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    file.write_all_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_write(buf, offset)? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            n => {
+                buf = &buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "compio"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use scraper::Html;
+
+    use super::*;
+    use crate::errors::{ExtractError, ScrapeError};
+    use crate::hoster::{BoxFuture, Hoster};
+    use crate::probe::ProbeInfo;
+    use crate::schedule::{Scheduler, SchedulerConfig};
+    use crate::testing::{Request, Response, TempDir, ddl, serve};
+
+    const SOURCE_URL: &str = "https://refresh.test/a.rar";
+
+    /// Resolves every link to `direct_link`, counting the refreshes
+    struct Refresher {
+        direct_link: String,
+        refreshes: Arc<AtomicUsize>,
+    }
+
+    impl Hoster for Refresher {
+        fn name(&self) -> &'static str {
+            "refresh"
+        }
+
+        fn owns_link(&self, url: &str) -> bool {
+            url.starts_with("https://refresh.test/")
+        }
+
+        fn discover_links(&self, _document: &Html) -> Result<Vec<String>, ScrapeError> {
+            Ok(Vec::new())
+        }
+
+        fn resolve_ddl<'a>(
+            &'a self,
+            _client: &'a FitgirlClient,
+            url: &'a str,
+        ) -> BoxFuture<'a, Result<DDL, ExtractError>> {
+            Box::pin(async move {
+                self.refreshes.fetch_add(1, Ordering::Relaxed);
+                Ok(DDL {
+                    direct_link: self.direct_link.clone(),
+                    source_url: url.to_string(),
+                    ..ddl("a.rar")
+                })
+            })
+        }
+    }
+
+    /// Client refreshing links to `direct_link`, and the count of refreshes
+    fn client(direct_link: String) -> (FitgirlClient, Arc<AtomicUsize>) {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let scheduler = Scheduler::new(SchedulerConfig {
+            rate: 1000.0,
+            burst: 100,
+            cooldown: Duration::ZERO,
+            ..Default::default()
+        });
+        let client = FitgirlClient::builder()
+            .hoster(Refresher {
+                direct_link,
+                refreshes: refreshes.clone(),
+            })
+            .scheduler(scheduler)
+            .build();
+        (client, refreshes)
+    }
+
+    fn data() -> Vec<u8> {
+        (0..64).collect()
+    }
+
+    /// `/old` has expired, `/new` serves [data] with range requests
+    fn respond(request: &Request) -> Option<Response> {
+        if request.path != "/new" {
+            return Some(Response::new(410, "gone"));
+        }
+        let data = data();
+        let Some(range) = request.header("range") else {
+            return Some(Response::new(200, data));
+        };
+        let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+        let start: usize = start.parse().ok()?;
+        let end: usize = end.parse().map_or(data.len(), |end: usize| end + 1);
+        Some(Response::new(206, &data[start..end]).header(
+            "Content-Range",
+            format!("bytes {start}-{}/{}", end - 1, data.len()),
+        ))
+    }
+
+    /// Link of [data] expiring at `base/old`, probed before it did
+    fn expiring_ddl(base: &str) -> DDL {
+        DDL {
+            direct_link: format!("{base}/old"),
+            source_url: SOURCE_URL.to_string(),
+            probe: Some(ProbeInfo {
+                status: 200,
+                content_length: Some(64),
+                accept_ranges: true,
+                ..Default::default()
+            }),
+            ..ddl("a.rar")
+        }
+    }
+
+    /// Leave `a.rar` in `dir` as a previous run stopped after `downloaded` bytes
+    fn interrupted(dir: &Path, downloaded: usize) {
+        let mut partial = data();
+        partial[downloaded..].fill(0);
+        std::fs::write(dir.join("a.rar.part"), partial).unwrap();
+        std::fs::write(
+            dir.join("a.rar.fgdl"),
+            format!("total 64\nsegment 0 64 {downloaded}\n"),
+        )
+        .unwrap();
+    }
+
+    /// Range headers of requests to `path`
+    fn ranges<'a>(requests: impl IntoIterator<Item = &'a Request>, path: &str) -> Vec<String> {
+        let mut ranges: Vec<_> = requests
+            .into_iter()
+            .filter(|request| request.method == "GET" && request.path == path)
+            .filter_map(|request| request.header("range").map(str::to_string))
+            .collect();
+        ranges.sort();
+        ranges
+    }
+
+    #[compio::test]
+    async fn refreshes_expired_link_and_resumes() {
+        let dir = TempDir::new();
+        let (base, requests) = serve(respond);
+        let (client, refreshes) = client(format!("{base}/new"));
+        interrupted(&dir, 24);
+
+        let options = DownloadOptions {
+            segments: 1,
+            ..Default::default()
+        };
+        let path = client
+            .download(&expiring_ddl(&base), &*dir, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read(path).unwrap(), data());
+        assert!(!dir.join("a.rar.fgdl").exists());
+        assert_eq!(refreshes.load(Ordering::Relaxed), 1);
+        let requests: Vec<_> = requests.try_iter().collect();
+        assert_eq!(ranges(&requests, "/old"), ["bytes=24-63"]);
+        assert_eq!(ranges(&requests, "/new"), ["bytes=24-63"]);
+    }
+
+    #[compio::test]
+    async fn gives_up_after_max_refreshes() {
+        let dir = TempDir::new();
+        let (base, requests) = serve(respond);
+        let (client, refreshes) = client(format!("{base}/old"));
+        interrupted(&dir, 24);
+
+        let options = DownloadOptions {
+            max_refreshes: 2,
+            ..Default::default()
+        };
+        let result = client.download(&expiring_ddl(&base), &*dir, &options).await;
+
+        assert!(matches!(result, Err(DownloadError::LinkExpired(410))));
+        assert_eq!(refreshes.load(Ordering::Relaxed), 2);
+        assert_eq!(requests.try_iter().count(), 3);
+        // the progress is kept for the next run
+        let state = std::fs::read_to_string(dir.join("a.rar.fgdl")).unwrap();
+        assert_eq!(state.parse::<DownloadState>().unwrap().downloaded(), 24);
+    }
+
+    #[compio::test]
+    async fn refreshes_every_segment() {
+        let dir = TempDir::new();
+        let (base, requests) = serve(respond);
+        let (client, refreshes) = client(format!("{base}/new"));
+
+        let options = DownloadOptions {
+            segments: 4,
+            min_segment_size: 16,
+            ..Default::default()
+        };
+        let path = client
+            .download(&expiring_ddl(&base), &*dir, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read(path).unwrap(), data());
+        assert_eq!(refreshes.load(Ordering::Relaxed), 1);
+        let requests: Vec<_> = requests.try_iter().collect();
+        assert_eq!(
+            ranges(&requests, "/new"),
+            ["bytes=0-15", "bytes=16-31", "bytes=32-47", "bytes=48-63"]
+        );
+    }
+}
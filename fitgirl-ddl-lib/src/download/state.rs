@@ -0,0 +1,214 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// End of a segment whose size is unknown, read until the response ends
+pub const UNKNOWN_END: u64 = u64::MAX;
+
+/// Byte range `start..end` of a file, and how much of it is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub start: u64,
+    /// Exclusive, [UNKNOWN_END] if the file size is unknown
+    pub end: u64,
+    pub downloaded: u64,
+}
+
+impl Segment {
+    /// Offset to continue writing at
+    pub fn position(&self) -> u64 {
+        self.start + self.downloaded
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.end != UNKNOWN_END && self.position() >= self.end
+    }
+}
+
+/// Progress of a download, persisted next to the partial file.
+///
+/// The text format is one `total <bytes|unknown>` line followed by
+/// `segment <start> <end> <downloaded>` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadState {
+    pub total: Option<u64>,
+    pub segments: Vec<Segment>,
+}
+
+impl DownloadState {
+    /// Split `total` bytes into at most `segments` parts of at least `min_segment_size`.
+    pub fn plan(total: Option<u64>, segments: usize, min_segment_size: u64) -> Self {
+        let Some(total) = total else {
+            return Self {
+                total,
+                segments: vec![Segment {
+                    start: 0,
+                    end: UNKNOWN_END,
+                    downloaded: 0,
+                }],
+            };
+        };
+
+        let count = (total / min_segment_size.max(1)).clamp(1, segments.max(1) as u64);
+        let size = total.div_ceil(count);
+        let segments = (0..count)
+            .map(|i| Segment {
+                start: i * size,
+                end: ((i + 1) * size).min(total),
+                downloaded: 0,
+            })
+            .filter(|segment| segment.start < segment.end || total == 0)
+            .collect();
+
+        Self {
+            total: Some(total),
+            segments,
+        }
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.downloaded).sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.segments.iter().all(Segment::is_complete)
+    }
+}
+
+impl Display for DownloadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.total {
+            Some(total) => writeln!(f, "total {total}")?,
+            None => writeln!(f, "total unknown")?,
+        }
+        for Segment {
+            start,
+            end,
+            downloaded,
+        } in &self.segments
+        {
+            writeln!(f, "segment {start} {end} {downloaded}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DownloadState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        let total = match lines.next().and_then(|line| line.strip_prefix("total ")) {
+            Some("unknown") => None,
+            Some(total) => Some(
+                total
+                    .parse()
+                    .map_err(|_| format!("invalid total: {total}"))?,
+            ),
+            None => return Err("total missing".to_string()),
+        };
+
+        let segments = lines
+            .map(|line| {
+                let fields = line
+                    .strip_prefix("segment ")
+                    .map(|fields| {
+                        fields
+                            .split_whitespace()
+                            .map(str::parse)
+                            .collect::<Result<Vec<u64>, _>>()
+                    })
+                    .and_then(Result::ok);
+                match fields.as_deref() {
+                    Some(&[start, end, downloaded]) if start.saturating_add(downloaded) <= end => {
+                        Ok(Segment {
+                            start,
+                            end,
+                            downloaded,
+                        })
+                    }
+                    _ => Err(format!("invalid segment: {line}")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if segments.is_empty() {
+            return Err("no segment".to_string());
+        }
+        if let Some(total) = total
+            && let Some(segment) = segments.iter().find(|segment| segment.end > total)
+        {
+            return Err(format!("segment ends at {} past total", segment.end));
+        }
+        Ok(Self { total, segments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_segments() {
+        let state = DownloadState::plan(Some(10), 3, 1);
+        let ranges: Vec<_> = state.segments.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(ranges, [(0, 4), (4, 8), (8, 10)]);
+
+        // too small to split
+        assert_eq!(DownloadState::plan(Some(10), 4, 8).segments.len(), 1);
+        assert_eq!(
+            DownloadState::plan(None, 4, 8).segments,
+            [Segment {
+                start: 0,
+                end: UNKNOWN_END,
+                downloaded: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut state = DownloadState::plan(Some(100), 2, 1);
+        state.segments[0].downloaded = 50;
+        state.segments[1].downloaded = 7;
+        assert_eq!(
+            state.to_string().parse::<DownloadState>(),
+            Ok(state.clone())
+        );
+        assert!(!state.is_complete());
+        assert_eq!(state.downloaded(), 57);
+
+        let unknown = DownloadState::plan(None, 1, 1);
+        assert_eq!(
+            unknown.to_string(),
+            format!("total unknown\nsegment 0 {UNKNOWN_END} 0\n")
+        );
+        assert_eq!(unknown.to_string().parse::<DownloadState>(), Ok(unknown));
+    }
+
+    #[test]
+    fn tolerates_blank_lines_and_spaces() {
+        let state = "\n total 4\n\n  segment 0 4 4  \n".parse::<DownloadState>();
+        assert!(state.is_ok_and(|state| state.is_complete()));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for text in [
+            "",
+            "segment 0 4 0",
+            "total",
+            "total -1\nsegment 0 4 0",
+            "total 4",
+            "total 4\nsegment 0 4",
+            "total 4\nsegment 0 4 0 0",
+            "total 4\nsegment 0 x 0",
+            "total 4\nsegment 2 4 3",
+            "total 4\nsegment 0 8 0",
+            "total 4\nsegments 0 4 0",
+            "total 4\nsegment 18446744073709551615 18446744073709551615 1",
+        ] {
+            assert!(text.parse::<DownloadState>().is_err(), "{text:?}");
+        }
+    }
+}
@@ -8,8 +8,15 @@ pub enum ScrapeError {
     DDoSGuarded,
     #[error("无 fuckingfast 下载源")]
     FuckingFastSourceMissing,
+    #[error("无法识别 {hoster} 下载源, 已尝试: {}", .strategies.join(", "))]
+    UnrecognizedLayout {
+        hoster: &'static str,
+        strategies: Vec<&'static str>,
+    },
     #[error("URL 格式错误: {0}")]
     IllFormedURI(#[from] http::uri::InvalidUri),
+    #[error("URL 格式错误: {0}")]
+    IllFormedURL(#[from] url::ParseError),
     #[error("请提供单个游戏的 URL")]
     UnexpectedURL,
     #[error("请求错误: {0}")]
@@ -38,10 +45,102 @@ pub enum ExtractError {
     RequestError(String),
     #[error("ill-formed uri: {0}")]
     IllFormedURI(#[from] http::uri::InvalidUri),
+    #[error("ill-formed url: {0}")]
+    IllFormedURL(#[from] url::ParseError),
     #[error("join error")]
     JoinError,
     #[error("rate limited")]
     RateLimited,
     #[error("file was deleted")]
     FileNotFound(String),
+    #[error("no registered hoster serves {0}")]
+    UnsupportedHoster(String),
+    #[error("no source url to refresh {0} from")]
+    SourceUrlMissing(String),
+}
+
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("blocked by ddos-guard")]
+    DDoSGuarded,
+    #[error("request: {0}")]
+    RequestError(String),
+    #[error("ill-formed uri: {0}")]
+    IllFormedURI(#[from] http::uri::InvalidUri),
+    #[error("ill-formed url: {0}")]
+    IllFormedURL(#[from] url::ParseError),
+    #[error("join error")]
+    JoinError,
+    #[error("invalid css selector")]
+    InvalidCSSSelector,
+}
+
+impl From<SelectorErrorKind<'_>> for SearchError {
+    fn from(_: SelectorErrorKind<'_>) -> Self {
+        Self::InvalidCSSSelector
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TorrentError {
+    #[error("request: {0}")]
+    RequestError(String),
+    #[error("ill-formed uri: {0}")]
+    IllFormedURI(#[from] http::uri::InvalidUri),
+    #[error("invalid bencode at byte {0}")]
+    InvalidBencode(usize),
+    #[error("torrent field missing: {0}")]
+    FieldMissing(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum DownloadError {
+    #[error("request: {0}")]
+    RequestError(String),
+    #[error("unexpected status: {0}")]
+    UnexpectedStatus(u16),
+    #[error("server ignored the range request")]
+    RangeIgnored,
+    #[error("direct link stopped working with status {0}")]
+    LinkExpired(u16),
+    #[error("incomplete download, received {received} of {expected} bytes")]
+    Incomplete { received: u64, expected: u64 },
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("join error")]
+    JoinError,
+    #[error(transparent)]
+    Extract(#[from] ExtractError),
+}
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("no .md5 file found in {0}")]
+    Md5FileMissing(String),
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("join error")]
+    JoinError,
+}
+
+#[cfg(feature = "aria2")]
+#[derive(Debug, Error)]
+pub enum Aria2Error {
+    #[error("request: {0}")]
+    RequestError(String),
+    #[error("request sent, but no response: {0}")]
+    NoResponse(String),
+    #[error("aria2 error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+#[cfg(feature = "jdownloader")]
+#[derive(Debug, Error)]
+pub enum ClickNLoadError {
+    #[error("request: {0}")]
+    RequestError(String),
+    #[error("rejected by JDownloader: {0}")]
+    Rejected(String),
 }
@@ -0,0 +1,95 @@
+use std::fmt::Write as _;
+
+use super::Exporter;
+use crate::extract::DDL;
+
+/// `.crawljob` file for the folder watch of JDownloader, one job per link.
+///
+/// All jobs join the same package, so JDownloader keeps the parts of a game together.
+#[derive(Debug, Clone, Default)]
+pub struct Crawljob {
+    /// Usually [GameInfo::path_part](crate::scrape::GameInfo::path_part)
+    pub package_name: Option<String>,
+}
+
+impl Exporter for Crawljob {
+    fn extension(&self) -> &'static str {
+        "crawljob"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        for (i, ddl) in ddls.iter().enumerate() {
+            if i > 0 {
+                _ = writeln!(output, "->NEW ENTRY<-");
+            }
+            _ = writeln!(output, "text={}", single_line(&ddl.direct_link));
+            _ = writeln!(output, "filename={}", single_line(&ddl.filename));
+            if let Some(package_name) = &self.package_name {
+                _ = writeln!(output, "packageName={}", single_line(package_name));
+            }
+            if let Some(dir) = dir {
+                _ = writeln!(output, "downloadFolder={}", single_line(dir));
+            }
+            _ = writeln!(output, "enabled=TRUE");
+            _ = writeln!(output, "autoConfirm=TRUE");
+        }
+    }
+}
+
+/// Values end at line breaks
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::export_ddls;
+    use crate::testing::ddl;
+
+    #[test]
+    fn separates_entries_of_one_package() {
+        let crawljob = Crawljob {
+            package_name: Some("Some Game".to_string()),
+        };
+        let ddls = [ddl("fg-10.bin"), ddl("fg-2.bin")];
+        assert_eq!(
+            export_ddls(&crawljob, &ddls, Some("/games/Some Game")),
+            "text=https://dl.example/fg-2.bin\n\
+             filename=fg-2.bin\n\
+             packageName=Some Game\n\
+             downloadFolder=/games/Some Game\n\
+             enabled=TRUE\n\
+             autoConfirm=TRUE\n\
+             ->NEW ENTRY<-\n\
+             text=https://dl.example/fg-10.bin\n\
+             filename=fg-10.bin\n\
+             packageName=Some Game\n\
+             downloadFolder=/games/Some Game\n\
+             enabled=TRUE\n\
+             autoConfirm=TRUE\n"
+        );
+    }
+
+    #[test]
+    fn keeps_values_on_one_line() {
+        let crawljob = Crawljob {
+            package_name: Some("Some\r\nGame".to_string()),
+        };
+        let output = export_ddls(&crawljob, &[ddl("a.rar")], None);
+        assert!(!output.contains("->NEW ENTRY<-"));
+        assert!(!output.contains("downloadFolder="));
+        assert_eq!(
+            output.lines().find(|line| line.starts_with("packageName=")),
+            Some("packageName=Some  Game")
+        );
+
+        assert_eq!(
+            export_ddls(&Crawljob::default(), &[ddl("a.rar")], None)
+                .lines()
+                .count(),
+            4
+        );
+        assert_eq!(export_ddls(&crawljob, &[], None), "");
+    }
+}
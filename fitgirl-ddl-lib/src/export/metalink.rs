@@ -0,0 +1,223 @@
+use std::fmt::Write as _;
+
+use super::{Exporter, join_dir};
+use crate::extract::DDL;
+use crate::torrent::{MagnetLink, TorrentInfo};
+use crate::verify::Checksum;
+
+/// Metalink 4 ([RFC 5854](https://www.rfc-editor.org/rfc/rfc5854)) document.
+///
+/// Each file has its direct link, and the magnet as a torrent `metaurl` if known.
+/// Sizes come from [DDL::probe] or the torrent, hashes from the repack's `.md5` file.
+#[derive(Debug, Clone, Default)]
+pub struct Metalink {
+    /// Matched with files by filename, see [parse_md5_file](crate::verify::parse_md5_file)
+    pub checksums: Vec<Checksum>,
+    pub magnet: Option<MagnetLink>,
+    /// File list of the torrent behind `magnet`, to name each part inside it
+    pub torrent: Option<TorrentInfo>,
+}
+
+impl Metalink {
+    /// Path of `filename` inside the torrent, as the `name` of its `metaurl`
+    fn torrent_path(&self, filename: &str) -> Option<String> {
+        match &self.torrent {
+            Some(torrent) => {
+                let file = torrent
+                    .files
+                    .iter()
+                    .find(|file| file.filename() == filename)?;
+                if torrent.files.len() == 1 && file.path == torrent.name {
+                    Some(file.path.clone())
+                } else {
+                    Some(format!("{}/{}", torrent.name, file.path))
+                }
+            }
+            None => {
+                let name = self.magnet.as_ref()?.display_name.as_ref()?;
+                Some(format!("{name}/{filename}"))
+            }
+        }
+    }
+
+    fn size(&self, ddl: &DDL) -> Option<u64> {
+        ddl.probe
+            .as_ref()
+            .and_then(|probe| probe.content_length)
+            .or_else(|| {
+                self.torrent
+                    .as_ref()?
+                    .files
+                    .iter()
+                    .find(|file| file.filename() == ddl.filename)
+                    .map(|file| file.length)
+            })
+    }
+}
+
+impl Exporter for Metalink {
+    fn extension(&self) -> &'static str {
+        "meta4"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        _ = writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        _ = writeln!(
+            output,
+            r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">"#
+        );
+        _ = writeln!(
+            output,
+            "  <generator>fitgirl-ddl/{}</generator>",
+            env!("CARGO_PKG_VERSION")
+        );
+
+        for ddl in ddls {
+            let name = join_dir(dir, &ddl.filename);
+            _ = writeln!(output, r#"  <file name="{}">"#, escape(&name));
+            if let Some(size) = self.size(ddl) {
+                _ = writeln!(output, "    <size>{size}</size>");
+            }
+            if let Some(checksum) = self
+                .checksums
+                .iter()
+                .find(|checksum| checksum.filename() == ddl.filename)
+            {
+                _ = writeln!(output, r#"    <hash type="md5">{}</hash>"#, checksum.md5);
+            }
+            _ = writeln!(
+                output,
+                r#"    <url priority="1">{}</url>"#,
+                escape(&ddl.direct_link)
+            );
+            if let Some(magnet) = &self.magnet
+                && let Some(path) = self.torrent_path(&ddl.filename)
+            {
+                _ = writeln!(
+                    output,
+                    r#"    <metaurl mediatype="torrent" priority="2" name="{}">{}</metaurl>"#,
+                    escape(&path),
+                    escape(&magnet.uri)
+                );
+            }
+            _ = writeln!(output, "  </file>");
+        }
+
+        _ = writeln!(output, "</metalink>");
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::export_ddls;
+    use crate::probe::ProbeInfo;
+    use crate::testing::ddl;
+    use crate::torrent::TorrentFile;
+
+    const MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+    fn head() -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <metalink xmlns=\"urn:ietf:params:xml:ns:metalink\">\n\
+             \x20 <generator>fitgirl-ddl/{}</generator>\n",
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    #[test]
+    fn escapes_names_and_urls() {
+        let ddl = DDL {
+            direct_link: r#"https://dl.example/a?b=1&c=<"2">"#.to_string(),
+            ..ddl(r#"Tom & "Jerry" <3>.rar"#)
+        };
+        assert_eq!(
+            export_ddls(&Metalink::default(), [&ddl], Some("Tom's Game")),
+            format!(
+                "{}\
+                 \x20 <file name=\"Tom&apos;s Game/Tom &amp; &quot;Jerry&quot; &lt;3&gt;.rar\">\n\
+                 \x20   <url priority=\"1\">https://dl.example/a?b=1&amp;c=&lt;&quot;2&quot;&gt;</url>\n\
+                 \x20 </file>\n\
+                 </metalink>\n",
+                head()
+            )
+        );
+    }
+
+    #[test]
+    fn adds_known_sizes_hashes_and_torrent_sources() {
+        let probed = DDL {
+            probe: Some(ProbeInfo {
+                status: 200,
+                content_length: Some(100),
+                accept_ranges: true,
+                content_type: None,
+                filename: None,
+            }),
+            ..ddl("fg-01.bin")
+        };
+        let metalink = Metalink {
+            checksums: vec![Checksum {
+                path: "MD5/fg-01.bin".to_string(),
+                md5: MD5.to_string(),
+            }],
+            magnet: Some(MagnetLink {
+                uri: "magnet:?xt=urn:btih:abc&dn=Some+Game".to_string(),
+                info_hash: "abc".to_string(),
+                display_name: Some("Some Game".to_string()),
+                trackers: Vec::new(),
+            }),
+            torrent: Some(TorrentInfo {
+                name: "Some Game".to_string(),
+                files: vec![
+                    TorrentFile {
+                        index: 1,
+                        path: "fg-01.bin".to_string(),
+                        length: 50,
+                    },
+                    TorrentFile {
+                        index: 2,
+                        path: "fg-02.bin".to_string(),
+                        length: 70,
+                    },
+                ],
+            }),
+        };
+
+        assert_eq!(
+            export_ddls(
+                &metalink,
+                [&ddl("fg-03.bin"), &ddl("fg-02.bin"), &probed],
+                None
+            ),
+            format!(
+                "{}\
+                 \x20 <file name=\"fg-01.bin\">\n\
+                 \x20   <size>100</size>\n\
+                 \x20   <hash type=\"md5\">{MD5}</hash>\n\
+                 \x20   <url priority=\"1\">https://dl.example/fg-01.bin</url>\n\
+                 \x20   <metaurl mediatype=\"torrent\" priority=\"2\" name=\"Some Game/fg-01.bin\">magnet:?xt=urn:btih:abc&amp;dn=Some+Game</metaurl>\n\
+                 \x20 </file>\n\
+                 \x20 <file name=\"fg-02.bin\">\n\
+                 \x20   <size>70</size>\n\
+                 \x20   <url priority=\"1\">https://dl.example/fg-02.bin</url>\n\
+                 \x20   <metaurl mediatype=\"torrent\" priority=\"2\" name=\"Some Game/fg-02.bin\">magnet:?xt=urn:btih:abc&amp;dn=Some+Game</metaurl>\n\
+                 \x20 </file>\n\
+                 \x20 <file name=\"fg-03.bin\">\n\
+                 \x20   <url priority=\"1\">https://dl.example/fg-03.bin</url>\n\
+                 \x20 </file>\n\
+                 </metalink>\n",
+                head()
+            )
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::extract::DDL;
+use crate::repack::natural_cmp;
+use crate::torrent::MagnetLink;
+
+mod crawljob;
+mod metalink;
+
+pub use crawljob::Crawljob;
+pub use metalink::Metalink;
+
+/// A file format telling a download tool where to save each direct link
+pub trait Exporter {
+    /// Extension of the output file, without the dot
+    fn extension(&self) -> &'static str;
+
+    /// Write entries of `ddls`, already in natural order of filenames, saved into `dir` if set.
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>);
+}
+
+/// Export `ddls` in natural order of filenames, downloaded into `dir` if set.
+pub fn export_ddls<'a>(
+    exporter: &(impl Exporter + ?Sized),
+    ddls: impl IntoIterator<Item = &'a DDL>,
+    dir: Option<&str>,
+) -> String {
+    let mut ddls: Vec<_> = ddls.into_iter().collect();
+    ddls.sort_by(|a, b| natural_cmp(&a.filename, &b.filename));
+
+    let mut output = String::new();
+    exporter.write_ddls(&mut output, &ddls, dir);
+    output
+}
+
+/// Input file of `aria2c -i`.
+///
+/// Files are saved as `out={dir}/{filename}`, relative to `aria2c -d`.
+/// An absolute `dir` can't be joined with it and is set as `dir=` instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aria2;
+
+impl Exporter for Aria2 {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        let absolute_dir = dir.filter(|dir| Path::new(dir).is_absolute());
+        for DDL {
+            filename,
+            direct_link,
+            ..
+        } in ddls
+        {
+            _ = writeln!(output, "{direct_link}");
+            match absolute_dir {
+                Some(dir) => {
+                    _ = writeln!(output, "    dir={dir}");
+                    _ = writeln!(output, "    out={filename}");
+                }
+                None => _ = writeln!(output, "    out={}", join_dir(dir, filename)),
+            }
+            _ = writeln!(output, "    continue=true");
+        }
+    }
+}
+
+/// One direct link per line, filenames and `dir` are dropped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UrlList;
+
+impl Exporter for UrlList {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], _dir: Option<&str>) {
+        for ddl in ddls {
+            _ = writeln!(output, "{}", ddl.direct_link);
+        }
+    }
+}
+
+/// Input file of `wget -i`, which has no per-link options.
+///
+/// Run it with `--content-disposition` to keep the filenames, and `-P` for the directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wget;
+
+impl Exporter for Wget {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        UrlList.write_ddls(output, ddls, dir);
+    }
+}
+
+/// Config file of `curl -K`, resuming partial files and creating `dir`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Curl;
+
+impl Exporter for Curl {
+    fn extension(&self) -> &'static str {
+        "conf"
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        _ = writeln!(output, "location");
+        _ = writeln!(output, "create-dirs");
+        _ = writeln!(output, "continue-at = -");
+        for ddl in ddls {
+            let path = join_dir(dir, &ddl.filename);
+            _ = writeln!(output);
+            _ = writeln!(output, "url = \"{}\"", curl_escape(&ddl.direct_link));
+            _ = writeln!(output, "output = \"{}\"", curl_escape(&path));
+        }
+    }
+}
+
+/// Shell of a [Script]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// Downloads with curl, resuming `.part` files
+    Bash,
+    /// Downloads with `Invoke-WebRequest`, which restarts `.part` files
+    PowerShell,
+}
+
+/// Standalone download script, skipping finished files when run again
+#[derive(Debug, Clone, Copy)]
+pub struct Script(pub Shell);
+
+impl Exporter for Script {
+    fn extension(&self) -> &'static str {
+        match self.0 {
+            Shell::Bash => "sh",
+            Shell::PowerShell => "ps1",
+        }
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        match self.0 {
+            Shell::Bash => {
+                _ = writeln!(output, "#!/usr/bin/env bash");
+                _ = writeln!(output, "set -euo pipefail");
+                if let Some(dir) = dir {
+                    _ = writeln!(output, "mkdir -p {}", bash_quote(dir));
+                }
+                for ddl in ddls {
+                    let path = join_dir(dir, &ddl.filename);
+                    let partial = bash_quote(&format!("{path}.part"));
+                    let path = bash_quote(&path);
+                    let link = bash_quote(&ddl.direct_link);
+                    _ = writeln!(
+                        output,
+                        "[ -e {path} ] || {{ curl -fL -C - -o {partial} {link} && mv {partial} {path}; }}"
+                    );
+                }
+            }
+            Shell::PowerShell => {
+                _ = writeln!(output, "$ErrorActionPreference = 'Stop'");
+                _ = writeln!(output, "$ProgressPreference = 'SilentlyContinue'");
+                if let Some(dir) = dir {
+                    _ = writeln!(
+                        output,
+                        "New-Item -ItemType Directory -Force -Path {} | Out-Null",
+                        powershell_quote(dir)
+                    );
+                }
+                for ddl in ddls {
+                    let path = join_dir(dir, &ddl.filename);
+                    let partial = powershell_quote(&format!("{path}.part"));
+                    let path = powershell_quote(&path);
+                    let link = powershell_quote(&ddl.direct_link);
+                    _ = writeln!(output, "if (-not (Test-Path -LiteralPath {path})) {{");
+                    _ = writeln!(
+                        output,
+                        "    Invoke-WebRequest -Uri {link} -OutFile {partial}"
+                    );
+                    _ = writeln!(
+                        output,
+                        "    Move-Item -Force -LiteralPath {partial} -Destination {path}"
+                    );
+                    _ = writeln!(output, "}}");
+                }
+            }
+        }
+    }
+}
+
+/// Built-in [Exporter]s, parsed from `aria2`, `urls`, `wget`, `curl`, `bash`, `powershell`, `metalink` or `crawljob`
+///
+/// [ExportFormat::Metalink] has no checksums or magnet, build a [Metalink] to include them,
+/// and [ExportFormat::Crawljob] has no package name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Aria2,
+    UrlList,
+    Wget,
+    Curl,
+    Script(Shell),
+    Metalink,
+    Crawljob,
+}
+
+impl Exporter for ExportFormat {
+    fn extension(&self) -> &'static str {
+        self.exporter().extension()
+    }
+
+    fn write_ddls(&self, output: &mut String, ddls: &[&DDL], dir: Option<&str>) {
+        self.exporter().write_ddls(output, ddls, dir);
+    }
+}
+
+static PLAIN_METALINK: Metalink = Metalink {
+    checksums: Vec::new(),
+    magnet: None,
+    torrent: None,
+};
+
+impl ExportFormat {
+    fn exporter(self) -> &'static dyn Exporter {
+        match self {
+            Self::Aria2 => &Aria2,
+            Self::UrlList => &UrlList,
+            Self::Wget => &Wget,
+            Self::Curl => &Curl,
+            Self::Script(Shell::Bash) => &Script(Shell::Bash),
+            Self::Script(Shell::PowerShell) => &Script(Shell::PowerShell),
+            Self::Metalink => &PLAIN_METALINK,
+            Self::Crawljob => &Crawljob { package_name: None },
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aria2" => Ok(Self::Aria2),
+            "urls" => Ok(Self::UrlList),
+            "wget" => Ok(Self::Wget),
+            "curl" => Ok(Self::Curl),
+            "bash" => Ok(Self::Script(Shell::Bash)),
+            "powershell" => Ok(Self::Script(Shell::PowerShell)),
+            "metalink" => Ok(Self::Metalink),
+            "crawljob" => Ok(Self::Crawljob),
+            _ => Err(format!("invalid export format: {s}")),
+        }
+    }
+}
+
+/// aria2 input entries for direct links in natural order of filenames, downloaded into `dir` if set.
+pub fn aria2_ddl_input<'a>(ddls: impl IntoIterator<Item = &'a DDL>, dir: Option<&str>) -> String {
+    export_ddls(&Aria2, ddls, dir)
+}
+
+/// aria2 input entries for magnets, downloaded into `dir` if set.
+pub fn aria2_magnet_input<'a>(
+    magnets: impl IntoIterator<Item = &'a MagnetLink>,
+    dir: Option<&str>,
+) -> String {
+    let mut output = String::new();
+    for MagnetLink { uri, .. } in magnets {
+        _ = writeln!(output, "{uri}");
+        if let Some(dir) = dir {
+            _ = writeln!(output, "    dir={dir}");
+        }
+        _ = writeln!(output, "    continue=true");
+    }
+    output
+}
+
+/// aria2 input entry for a `.torrent` url or magnet, only downloading `selected` files.
+///
+/// `selected` are 1-based file indices, see [TorrentInfo::select_files](crate::torrent::TorrentInfo::select_files).
+pub fn aria2_torrent_input(torrent: &str, selected: &[usize], dir: Option<&str>) -> String {
+    let mut output = format!("{torrent}\n");
+    if !selected.is_empty() {
+        _ = writeln!(output, "    select-file={}", index_ranges(selected));
+    }
+    if let Some(dir) = dir {
+        _ = writeln!(output, "    dir={dir}");
+    }
+    _ = writeln!(output, "    continue=true");
+    output
+}
+
+/// `[1, 2, 3, 5]` => `1-3,5`
+fn index_ranges(indices: &[usize]) -> String {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn join_dir(dir: Option<&str>, filename: &str) -> String {
+    match dir {
+        Some(dir) => format!("{}/{filename}", dir.trim_end_matches(['/', '\\'])),
+        None => filename.to_string(),
+    }
+}
+
+fn curl_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn bash_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ddl;
+
+    /// A filename tripping up naive quoting
+    const ODD_NAME: &str = r#"it's "$HOME" \ `x`.rar"#;
+
+    #[test]
+    fn writes_aria2_paths_relative_to_its_dir() {
+        let ddls = [ddl("fg-10.bin"), ddl("fg-2.bin")];
+        assert_eq!(
+            export_ddls(&Aria2, &ddls, Some("Some Game/")),
+            "https://dl.example/fg-2.bin\n\
+             \x20   out=Some Game/fg-2.bin\n\
+             \x20   continue=true\n\
+             https://dl.example/fg-10.bin\n\
+             \x20   out=Some Game/fg-10.bin\n\
+             \x20   continue=true\n"
+        );
+        assert_eq!(
+            export_ddls(&Aria2, &ddls[..1], None),
+            "https://dl.example/fg-10.bin\n    out=fg-10.bin\n    continue=true\n"
+        );
+        assert_eq!(
+            aria2_ddl_input(&ddls[..1], None),
+            export_ddls(&Aria2, &ddls[..1], None)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sets_absolute_aria2_dir() {
+        assert_eq!(
+            export_ddls(&Aria2, &[ddl("a.rar")], Some("/games/Some Game")),
+            "https://dl.example/a.rar\n\
+             \x20   dir=/games/Some Game\n\
+             \x20   out=a.rar\n\
+             \x20   continue=true\n"
+        );
+    }
+
+    #[test]
+    fn lists_only_urls() {
+        let ddls = [ddl("b.rar"), ddl("a.rar")];
+        let urls = "https://dl.example/a.rar\nhttps://dl.example/b.rar\n";
+        assert_eq!(export_ddls(&UrlList, &ddls, Some("Some Game")), urls);
+        assert_eq!(export_ddls(&Wget, &ddls, Some("Some Game")), urls);
+        assert_eq!(export_ddls(&UrlList, &[], None), "");
+    }
+
+    #[test]
+    fn escapes_curl_config_strings() {
+        assert_eq!(
+            export_ddls(&Curl, &[ddl(ODD_NAME)], Some("Some Game")),
+            r#"location
+create-dirs
+continue-at = -
+
+url = "https://dl.example/it's \"$HOME\" \\ `x`.rar"
+output = "Some Game/it's \"$HOME\" \\ `x`.rar"
+"#
+        );
+        assert_eq!(curl_escape(r#"a\"b"#), r#"a\\\"b"#);
+    }
+
+    #[test]
+    fn quotes_bash_script_words() {
+        assert_eq!(bash_quote("plain"), "'plain'");
+        assert_eq!(bash_quote("it's $HOME"), r"'it'\''s $HOME'");
+
+        let script = export_ddls(&Script(Shell::Bash), &[ddl(ODD_NAME)], Some("Game's $dir"));
+        let path = r#"'Game'\''s $dir/it'\''s "$HOME" \ `x`.rar'"#;
+        let partial = r#"'Game'\''s $dir/it'\''s "$HOME" \ `x`.rar.part'"#;
+        let link = r#"'https://dl.example/it'\''s "$HOME" \ `x`.rar'"#;
+        assert_eq!(
+            script,
+            format!(
+                "#!/usr/bin/env bash\n\
+                 set -euo pipefail\n\
+                 mkdir -p 'Game'\\''s $dir'\n\
+                 [ -e {path} ] || {{ curl -fL -C - -o {partial} {link} && mv {partial} {path}; }}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn quotes_powershell_script_words() {
+        assert_eq!(powershell_quote("it's $HOME"), "'it''s $HOME'");
+
+        let script = export_ddls(&Script(Shell::PowerShell), &[ddl(ODD_NAME)], None);
+        let path = r#"'it''s "$HOME" \ `x`.rar'"#;
+        let partial = r#"'it''s "$HOME" \ `x`.rar.part'"#;
+        let link = r#"'https://dl.example/it''s "$HOME" \ `x`.rar'"#;
+        assert_eq!(
+            script,
+            format!(
+                "$ErrorActionPreference = 'Stop'\n\
+                 $ProgressPreference = 'SilentlyContinue'\n\
+                 if (-not (Test-Path -LiteralPath {path})) {{\n\
+                 \x20   Invoke-WebRequest -Uri {link} -OutFile {partial}\n\
+                 \x20   Move-Item -Force -LiteralPath {partial} -Destination {path}\n\
+                 }}\n"
+            )
+        );
+        assert!(
+            export_ddls(&Script(Shell::PowerShell), &[], Some("C:\\Games\\It's")).contains(
+                "New-Item -ItemType Directory -Force -Path 'C:\\Games\\It''s' | Out-Null"
+            )
+        );
+    }
+
+    #[test]
+    fn parses_formats() {
+        for (name, extension) in [
+            ("aria2", "txt"),
+            ("urls", "txt"),
+            ("wget", "txt"),
+            ("curl", "conf"),
+            ("bash", "sh"),
+            ("powershell", "ps1"),
+            ("metalink", "meta4"),
+            ("crawljob", "crawljob"),
+        ] {
+            let format: ExportFormat = name.parse().unwrap();
+            assert_eq!(format.extension(), extension, "{name}");
+        }
+        assert!("zip".parse::<ExportFormat>().is_err());
+    }
+}
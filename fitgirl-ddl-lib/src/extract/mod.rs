@@ -1,82 +1,204 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
 use http::header::{CONTENT_TYPE, COOKIE, ORIGIN};
 use http::{HeaderValue, Method, Uri};
 use tracing::debug;
+use url::Url;
 
-use crate::HTTP_CLIENT;
 use crate::errors::ExtractError;
+use crate::probe::ProbeInfo;
+use crate::schedule::host_key;
+use crate::{FitgirlClient, default_client};
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DDL {
     pub filename: String,
     pub direct_link: String,
+    /// The hoster page `direct_link` was resolved from, used by [FitgirlClient::refresh_ddl].
+    ///
+    /// Empty for links saved before it was recorded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_url: String,
+    /// The unix epoch for links saved before it was recorded, so they count as old
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fetched_at: DateTime<Utc>,
+    /// Parsed from `direct_link` if it carries an expiry
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Filled by [FitgirlClient::probe_ddl]
+    pub probe: Option<ProbeInfo>,
+}
+
+impl DDL {
+    /// Whether `direct_link` has passed its expiry, `false` if unknown
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= DateTime::<Utc>::from(SystemTime::now()))
+    }
+}
+
+/// Query parameters of direct links which may carry a unix timestamp of expiry
+const EXPIRY_PARAMS: &[&str] = &["expires", "expire", "expiry", "exp"];
+
+/// Expiry of a direct link, from query parameters like `?expires=1700000000`
+pub fn parse_expiry(direct_link: &str) -> Option<DateTime<Utc>> {
+    let url = Url::parse(direct_link).ok()?;
+    url.query_pairs()
+        .filter(|(key, _)| EXPIRY_PARAMS.contains(&key.to_ascii_lowercase().as_str()))
+        .find_map(|(_, value)| value.parse::<i64>().ok())
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
 }
 
+/// Extract with [default_client], using `cookies` for fuckingfast.co.
 pub async fn extract_ddl(url: impl AsRef<str>, cookies: &str) -> Result<DDL, ExtractError> {
-    let url = url.as_ref();
-
-    let filename = url
-        .split('#')
-        .nth(1)
-        .ok_or(ExtractError::FilenameMissing)?
-        .to_string();
-    let uri: Uri = url.parse()?;
-
-    // Step 1: GET request to check file status
-    let resp = HTTP_CLIENT
-        .request(Method::GET, uri.clone())
-        .send()
+    let cookies = HeaderValue::from_str(cookies)?;
+    default_client()
+        .extract_ddl_scheduled(url.as_ref(), Some(cookies))
         .await
-        .map_err(|e| ExtractError::RequestError(e.to_string()))?
-        .text()
-        .await
-        .map_err(|e| ExtractError::RequestError(e.to_string()))?;
+}
+
+/// Refresh with [default_client], using `cookies` for fuckingfast.co.
+pub async fn refresh_ddl(ddl: &DDL, cookies: &str) -> Result<DDL, ExtractError> {
+    let cookies = HeaderValue::from_str(cookies)?;
+    let default = default_client();
+    // same connections and rate limits as default_client, only the cookies differ
+    let client = FitgirlClient::builder()
+        .http_client(default.http().clone())
+        .fuckingfast_base(default.fuckingfast_base().clone())
+        .fuckingfast_cookies(cookies)
+        .scheduler(default.scheduler().clone())
+        .build();
+    client.refresh_ddl(ddl).await
+}
 
-    if resp.contains("rate limit") {
-        return Err(ExtractError::RateLimited);
+impl FitgirlClient {
+    /// Resolve [DDL::source_url] again, for links which expired or stopped working.
+    pub async fn refresh_ddl(&self, ddl: &DDL) -> Result<DDL, ExtractError> {
+        if ddl.source_url.is_empty() {
+            return Err(ExtractError::SourceUrlMissing(ddl.filename.clone()));
+        }
+        debug!("refreshing {} fetched at {}", ddl.filename, ddl.fetched_at);
+        self.resolve_ddl(&ddl.source_url).await
     }
 
-    if resp.contains("File Not Found Or Deleted") {
-        return Err(ExtractError::FileNotFound(filename));
+    /// Extract through [FitgirlClient::scheduler], waiting if fuckingfast is busy or rate limited.
+    pub async fn extract_ddl(&self, url: impl AsRef<str>) -> Result<DDL, ExtractError> {
+        self.extract_ddl_scheduled(url.as_ref(), self.fuckingfast_cookies())
+            .await
     }
 
-    // Step 2 & 3: Extract file ID and POST to download endpoint
-    let file_id = uri.path().trim_start_matches('/');
-    let post_uri: Uri = format!("https://fuckingfast.co/f/{file_id}/go").parse()?;
-
-    let mut req = wreq::Request::new(Method::POST, post_uri);
-    req.headers_mut()
-        .insert("HX-Request", HeaderValue::from_static("true"));
-    req.headers_mut().insert(
-        "HX-Current-URL",
-        HeaderValue::from_str(url).map_err(|e| ExtractError::RequestError(e.to_string()))?,
-    );
-    req.headers_mut()
-        .insert(ORIGIN, HeaderValue::from_static("https://fuckingfast.co"));
-    req.headers_mut().insert(
-        CONTENT_TYPE,
-        HeaderValue::from_static("application/x-www-form-urlencoded"),
-    );
-    req.headers_mut()
-        .insert(COOKIE, HeaderValue::from_str(cookies)?);
-
-    let post_resp = HTTP_CLIENT
-        .execute(req)
-        .await
-        .map_err(|e| ExtractError::RequestError(e.to_string()))?;
-
-    let hx_redirect = post_resp.headers().get("HX-Redirect").cloned();
-    debug!("Response: {:?}", post_resp.bytes().await);
-
-    // Step 4: Read HX-Redirect header
-    let direct_link = hx_redirect
-        .ok_or(ExtractError::DDLMissing)?
-        .to_str()
-        .map_err(|_| ExtractError::DDLMissing)?
-        .to_string();
-
-    Ok(DDL {
-        filename,
-        direct_link,
-    })
+    async fn extract_ddl_scheduled(
+        &self,
+        url: &str,
+        cookies: Option<HeaderValue>,
+    ) -> Result<DDL, ExtractError> {
+        self.scheduler()
+            .run(&host_key(url), async move || {
+                self.extract_ddl_inner(url, cookies).await
+            })
+            .await
+    }
+
+    /// Extract without waiting for [FitgirlClient::scheduler]
+    pub(crate) async fn extract_ddl_inner(
+        &self,
+        url: &str,
+        cookies: Option<HeaderValue>,
+    ) -> Result<DDL, ExtractError> {
+        let filename = url
+            .split('#')
+            .nth(1)
+            .ok_or(ExtractError::FilenameMissing)?
+            .to_string();
+        let uri: Uri = url.parse()?;
+
+        // Step 1: GET request to check file status
+        let resp = self
+            .http()
+            .request(Method::GET, uri.clone())
+            .send()
+            .await
+            .map_err(|e| ExtractError::RequestError(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| ExtractError::RequestError(e.to_string()))?;
+
+        if resp.contains("rate limit") {
+            return Err(ExtractError::RateLimited);
+        }
+
+        if resp.contains("File Not Found Or Deleted") {
+            return Err(ExtractError::FileNotFound(filename));
+        }
+
+        // Step 2 & 3: Extract file ID and POST to download endpoint
+        let file_id = uri.path().trim_start_matches('/');
+        let post_uri: Uri = self
+            .fuckingfast_base()
+            .join(&format!("f/{file_id}/go"))?
+            .as_str()
+            .parse()?;
+        let origin = self.fuckingfast_base().origin().ascii_serialization();
+
+        let mut req = wreq::Request::new(Method::POST, post_uri);
+        req.headers_mut()
+            .insert("HX-Request", HeaderValue::from_static("true"));
+        req.headers_mut().insert(
+            "HX-Current-URL",
+            HeaderValue::from_str(url).map_err(|e| ExtractError::RequestError(e.to_string()))?,
+        );
+        req.headers_mut()
+            .insert(ORIGIN, HeaderValue::from_str(&origin)?);
+        req.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        if let Some(cookies) = cookies {
+            req.headers_mut().insert(COOKIE, cookies);
+        }
+
+        let post_resp = self
+            .http()
+            .execute(req)
+            .await
+            .map_err(|e| ExtractError::RequestError(e.to_string()))?;
+
+        let hx_redirect = post_resp.headers().get("HX-Redirect").cloned();
+        debug!("Response: {:?}", post_resp.bytes().await);
+
+        // Step 4: Read HX-Redirect header
+        let direct_link = hx_redirect
+            .ok_or(ExtractError::DDLMissing)?
+            .to_str()
+            .map_err(|_| ExtractError::DDLMissing)?
+            .to_string();
+
+        Ok(DDL {
+            filename,
+            expires_at: parse_expiry(&direct_link),
+            direct_link,
+            source_url: url.to_string(),
+            fetched_at: SystemTime::now().into(),
+            probe: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_expiry() {
+        assert_eq!(
+            parse_expiry("https://dl.example/a.rar?token=x&Expires=1700000000"),
+            DateTime::from_timestamp(1700000000, 0)
+        );
+        assert_eq!(parse_expiry("https://dl.example/a.rar?exp=soon"), None);
+        // too short to be sure it is an expiry
+        assert_eq!(parse_expiry("https://dl.example/a.rar?e=1700000000"), None);
+        assert_eq!(parse_expiry("not a url"), None);
+    }
 }
@@ -0,0 +1,258 @@
+use scraper::{ElementRef, Html, Selector};
+use tracing::debug;
+use url::Url;
+
+use crate::FitgirlClient;
+use crate::errors::{ExtractError, ScrapeError};
+use crate::extract::DDL;
+use crate::hoster::{BoxFuture, Hoster};
+
+/// fuckingfast.co, links are resolved with [FitgirlClient::extract_ddl]
+#[derive(Debug, Clone)]
+pub struct FuckingFast {
+    domain: String,
+}
+
+impl Default for FuckingFast {
+    fn default() -> Self {
+        Self::with_domain("fuckingfast.co")
+    }
+}
+
+type Strategy = fn(&FuckingFast, &Html) -> Result<Vec<String>, ScrapeError>;
+
+impl FuckingFast {
+    pub const NAME: &'static str = "fuckingfast";
+
+    /// Tried in order, until one of them finds links
+    const STRATEGIES: &'static [(&'static str, Strategy)] = &[
+        ("spoiler-title", Self::from_spoiler_title),
+        ("mirror-item", Self::from_mirror_item),
+        ("link-domain", Self::from_link_domain),
+    ];
+
+    pub fn with_domain(domain: impl Into<String>) -> Self {
+        Self {
+            domain: domain.into(),
+        }
+    }
+
+    /// Spoilers titled like `Filehoster: FuckingFast`
+    fn from_spoiler_title(&self, document: &Html) -> Result<Vec<String>, ScrapeError> {
+        let spoiler_selector = Selector::parse("div.entry-content div.su-spoiler")?;
+        let title_selector = Selector::parse("div.su-spoiler-title")?;
+        let content_selector = Selector::parse("div.su-spoiler-content")?;
+
+        Ok(document
+            .select(&spoiler_selector)
+            .filter(|spoiler| {
+                spoiler.select(&title_selector).any(|title| {
+                    title
+                        .text()
+                        .collect::<String>()
+                        .to_ascii_lowercase()
+                        .contains(Self::NAME)
+                })
+            })
+            .flat_map(|spoiler| spoiler.select(&content_selector))
+            .flat_map(|content| self.owned_links(content))
+            .collect())
+    }
+
+    /// Mirror list items linking to fuckingfast, with or without a spoiler of parts.
+    ///
+    /// Only top-level items, links of nested lists are already in their parent item.
+    fn from_mirror_item(&self, document: &Html) -> Result<Vec<String>, ScrapeError> {
+        let item_selector = Selector::parse("div.entry-content > ul > li")?;
+        let anchor_selector = Selector::parse("a")?;
+
+        let mentions_hoster = |item: &ElementRef<'_>| {
+            item.select(&anchor_selector).any(|a| {
+                a.attr("href").is_some_and(|href| self.owns_link(href))
+                    || a.text()
+                        .collect::<String>()
+                        .to_ascii_lowercase()
+                        .contains(Self::NAME)
+            })
+        };
+
+        Ok(document
+            .select(&item_selector)
+            .filter(mentions_hoster)
+            .flat_map(|item| self.owned_links(item))
+            .collect())
+    }
+
+    /// Any link to fuckingfast in the post
+    fn from_link_domain(&self, document: &Html) -> Result<Vec<String>, ScrapeError> {
+        let content_selector = Selector::parse("div.entry-content")?;
+
+        Ok(document
+            .select(&content_selector)
+            .flat_map(|content| self.owned_links(content))
+            .collect())
+    }
+
+    fn owned_links(&self, element: ElementRef<'_>) -> Vec<String> {
+        element
+            .descendent_elements()
+            .filter(|e| e.value().name() == "a")
+            .filter_map(|a| a.attr("href"))
+            .filter(|href| self.owns_link(href))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Hoster for FuckingFast {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn owns_link(&self, url: &str) -> bool {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.host_str()
+                    .map(|host| host.strip_prefix("www.").unwrap_or(host) == self.domain)
+            })
+            .unwrap_or_default()
+    }
+
+    fn discover_links(&self, document: &Html) -> Result<Vec<String>, ScrapeError> {
+        for (name, strategy) in Self::STRATEGIES {
+            // page order, duplicates are kept for check_completeness to report
+            let links = strategy(self, document)?;
+            if links.is_empty() {
+                debug!("fuckingfast discovery strategy `{name}` found nothing");
+                continue;
+            }
+
+            return Ok(links);
+        }
+
+        Err(ScrapeError::UnrecognizedLayout {
+            hoster: Self::NAME,
+            strategies: Self::STRATEGIES.iter().map(|(name, _)| *name).collect(),
+        })
+    }
+
+    fn resolve_ddl<'a>(
+        &'a self,
+        client: &'a FitgirlClient,
+        url: &'a str,
+    ) -> BoxFuture<'a, Result<DDL, ExtractError>> {
+        Box::pin(client.extract_ddl_inner(url, client.fuckingfast_cookies()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discover(html: &str) -> Result<Vec<String>, ScrapeError> {
+        FuckingFast::default().discover_links(&Html::parse_document(html))
+    }
+
+    #[test]
+    fn prefers_spoiler_titled_with_hoster() {
+        let links = discover(
+            r#"<div class="entry-content">
+            <p><a href="https://fuckingfast.co/zz#elsewhere.rar">elsewhere</a></p>
+            <div class="su-spoiler">
+              <div class="su-spoiler-title">Filehoster: DataNodes</div>
+              <div class="su-spoiler-content"><a href="https://datanodes.to/x">x</a></div>
+            </div>
+            <div class="su-spoiler">
+              <div class="su-spoiler-title">Filehoster: FuckingFast</div>
+              <div class="su-spoiler-content">
+                <a href="https://fuckingfast.co/a1#game.part1.rar">part1</a><br>
+                <a href="https://fuckingfast.co/a2#game.part2.rar">part2</a>
+              </div>
+            </div>
+            </div>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            links,
+            [
+                "https://fuckingfast.co/a1#game.part1.rar",
+                "https://fuckingfast.co/a2#game.part2.rar"
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_mirror_items_once_each() {
+        let links = discover(
+            r#"<div class="entry-content">
+            <ul>
+              <li><a href="https://1fichier.com/x">1fichier</a></li>
+              <li>FuckingFast
+                <ul>
+                  <li><a href="https://www.fuckingfast.co/a1#game.part1.rar">part1</a></li>
+                  <li><a href="https://fuckingfast.co/a2#game.part2.rar">part2</a></li>
+                </ul>
+              </li>
+            </ul>
+            <p><a href="https://fuckingfast.co/zz#elsewhere.rar">elsewhere</a></p>
+            </div>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            links,
+            [
+                "https://www.fuckingfast.co/a1#game.part1.rar",
+                "https://fuckingfast.co/a2#game.part2.rar"
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_any_link_of_the_domain() {
+        let links = discover(
+            r#"<div class="entry-content">
+            <p>Parts:
+              <a href="https://fuckingfast.co/a1#game.part1.rar">part1</a>
+              <a href="https://fuckingfast.co.evil.example/a2#game.part2.rar">fake</a>
+              <a href="https://fuckingfast.co/a1#game.part1.rar">part1 again</a>
+            </p>
+            </div>"#,
+        )
+        .unwrap();
+        // repeats are kept for check_completeness
+        assert_eq!(
+            links,
+            [
+                "https://fuckingfast.co/a1#game.part1.rar",
+                "https://fuckingfast.co/a1#game.part1.rar"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unrecognized_layout() {
+        let result = discover(
+            r#"<div class="entry-content">
+            <ul><li><a href="https://1fichier.com/x">1fichier</a></li></ul>
+            </div>"#,
+        );
+        assert!(
+            matches!(
+                &result,
+                Err(ScrapeError::UnrecognizedLayout { hoster: FuckingFast::NAME, strategies })
+                    if strategies == &["spoiler-title", "mirror-item", "link-domain"]
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn owns_links_of_its_domain() {
+        let hoster = FuckingFast::with_domain("ff.test");
+        assert!(hoster.owns_link("https://ff.test/abc#a.rar"));
+        assert!(hoster.owns_link("https://www.ff.test/abc#a.rar"));
+        assert!(!hoster.owns_link("https://fuckingfast.co/abc#a.rar"));
+        assert!(!hoster.owns_link("not a url"));
+    }
+}
@@ -0,0 +1,58 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use scraper::Html;
+
+use crate::FitgirlClient;
+use crate::errors::{ExtractError, ScrapeError};
+use crate::extract::DDL;
+use crate::schedule::host_key;
+
+mod fuckingfast;
+
+pub use fuckingfast::FuckingFast;
+
+/// Future of [Hoster::resolve_ddl], `Send` so it can run on multi-threaded runtimes
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A file hoster listed in "Download Mirrors" of game pages.
+///
+/// Register extra hosters with [FitgirlClientBuilder::hoster](crate::FitgirlClientBuilder::hoster).
+pub trait Hoster: Send + Sync {
+    /// Unique name, also the key of [GameInfo::hoster_links](crate::scrape::GameInfo::hoster_links)
+    fn name(&self) -> &'static str;
+
+    /// Whether `url` is served by this hoster
+    fn owns_link(&self, url: &str) -> bool;
+
+    /// Find links of this hoster in a game page
+    fn discover_links(&self, document: &Html) -> Result<Vec<String>, ScrapeError>;
+
+    /// Resolve a link returned by [Hoster::discover_links] into a direct download link
+    ///
+    /// Called by [FitgirlClient::resolve_ddl] which already waits for the scheduler.
+    fn resolve_ddl<'a>(
+        &'a self,
+        client: &'a FitgirlClient,
+        url: &'a str,
+    ) -> BoxFuture<'a, Result<DDL, ExtractError>>;
+}
+
+impl FitgirlClient {
+    /// Find the registered hoster serving `url`
+    pub fn hoster_of(&self, url: &str) -> Option<&Arc<dyn Hoster>> {
+        self.hosters().iter().find(|hoster| hoster.owns_link(url))
+    }
+
+    /// Resolve `url` with the hoster serving it, through [FitgirlClient::scheduler].
+    pub async fn resolve_ddl(&self, url: impl AsRef<str>) -> Result<DDL, ExtractError> {
+        let url = url.as_ref();
+        let hoster = self
+            .hoster_of(url)
+            .ok_or_else(|| ExtractError::UnsupportedHoster(url.to_string()))?;
+        self.scheduler()
+            .run(&host_key(url), async || hoster.resolve_ddl(self, url).await)
+            .await
+    }
+}
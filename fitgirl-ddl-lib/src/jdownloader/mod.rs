@@ -0,0 +1,197 @@
+use aes::cipher::block_padding::ZeroPadding;
+use aes::cipher::{BlockEncryptMut, KeyIvInit};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use http::header::CONTENT_TYPE;
+use tracing::debug;
+use url::form_urlencoded;
+use wreq::Client;
+
+use crate::errors::ClickNLoadError;
+use crate::extract::DDL;
+use crate::random::random_u64;
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+
+/// Click'n'Load v2 client, handing links to a running JDownloader.
+///
+/// Links are sent AES encrypted to `/flash/addcrypted2`, as browsers do from link protection sites.
+pub struct ClickNLoad {
+    endpoint: String,
+    http: Client,
+}
+
+impl Default for ClickNLoad {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_ENDPOINT)
+    }
+}
+
+impl ClickNLoad {
+    /// JDownloader only listens on localhost by default
+    pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:9666";
+
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            http: Client::new(),
+        }
+    }
+
+    /// Whether JDownloader answers on the endpoint
+    pub async fn is_running(&self) -> bool {
+        let resp = self
+            .http
+            .get(format!("{}/jdcheck.js", self.endpoint))
+            .send()
+            .await;
+        match resp {
+            Ok(resp) => resp
+                .text()
+                .await
+                .is_ok_and(|text| text.contains("jdownloader=true")),
+            Err(e) => {
+                debug!("no JDownloader at {}: {e}", self.endpoint);
+                false
+            }
+        }
+    }
+
+    /// Add `links` to JDownloader as one package
+    pub async fn add_links<'a>(
+        &self,
+        package_name: &str,
+        links: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ClickNLoadError> {
+        let links = links.into_iter().collect::<Vec<_>>().join("\r\n");
+        let key = random_key();
+        let crypted = Aes128CbcEnc::new(&key.into(), &key.into())
+            .encrypt_padded_vec_mut::<ZeroPadding>(links.as_bytes());
+
+        let hex_key: String = key.iter().map(|b| format!("{b:02x}")).collect();
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("source", "fitgirl-ddl")
+            .append_pair("package", package_name)
+            .append_pair("passwords", "")
+            .append_pair("jk", &format!("function f(){{ return '{hex_key}'; }}"))
+            .append_pair("crypted", &STANDARD.encode(crypted))
+            .finish();
+
+        let text = self
+            .http
+            .post(format!("{}/flash/addcrypted2", self.endpoint))
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ClickNLoadError::RequestError(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| ClickNLoadError::RequestError(e.to_string()))?;
+
+        if text.trim() == "success" {
+            Ok(())
+        } else {
+            Err(ClickNLoadError::Rejected(text.trim().to_string()))
+        }
+    }
+
+    /// Add direct links of `ddls` to JDownloader as one package
+    pub async fn add_ddls<'a>(
+        &self,
+        package_name: &str,
+        ddls: impl IntoIterator<Item = &'a DDL>,
+    ) -> Result<(), ClickNLoadError> {
+        self.add_links(
+            package_name,
+            ddls.into_iter().map(|ddl| ddl.direct_link.as_str()),
+        )
+        .await
+    }
+}
+
+/// Key of a single request, which is sent along the data and only hides it from casual looks
+fn random_key() -> [u8; 16] {
+    let mut key = [0; 16];
+    for chunk in key.chunks_mut(8) {
+        chunk.copy_from_slice(&random_u64().to_le_bytes());
+    }
+    key
+}
+
+#[cfg(all(test, feature = "compio"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use aes::cipher::BlockDecryptMut;
+
+    use super::*;
+    use crate::testing::{closed_endpoint, serve};
+
+    type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+    /// Links hidden in a Click'n'Load form, decrypted like JDownloader does
+    fn decrypt_links(form: &HashMap<String, String>) -> String {
+        let hex_key = form["jk"].split('\'').nth(1).unwrap();
+        let key: [u8; 16] = (0..hex_key.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex_key[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let crypted = STANDARD.decode(&form["crypted"]).unwrap();
+        let links = Aes128CbcDec::new(&key.into(), &key.into())
+            .decrypt_padded_vec_mut::<ZeroPadding>(&crypted)
+            .unwrap();
+        String::from_utf8(links).unwrap()
+    }
+
+    #[compio::test]
+    async fn sends_encrypted_package() {
+        let (base, requests) = serve(|request| {
+            Some(match request.path.as_str() {
+                "/jdcheck.js" => "jdownloader=true;\r\nvar version='1';".to_string(),
+                _ => "success\r\n".to_string(),
+            })
+        });
+        let jdownloader = ClickNLoad::new(format!("{base}/"));
+
+        assert!(jdownloader.is_running().await);
+        let request = requests.recv().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("GET", "/jdcheck.js")
+        );
+
+        let links = [
+            "https://dl.example/a.part1.rar",
+            "https://dl.example/a.part2.rar",
+        ];
+        jdownloader.add_links("Some Game", links).await.unwrap();
+        let request = requests.recv().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/flash/addcrypted2")
+        );
+        let form: HashMap<String, String> = form_urlencoded::parse(request.body.as_bytes())
+            .into_owned()
+            .collect();
+        assert_eq!(form["source"], "fitgirl-ddl");
+        assert_eq!(form["package"], "Some Game");
+        assert_eq!(decrypt_links(&form), links.join("\r\n"));
+    }
+
+    #[compio::test]
+    async fn reports_rejection_and_absence() {
+        let (base, _requests) = serve(|_| Some("failed".to_string()));
+        let result = ClickNLoad::new(base)
+            .add_links("Some Game", ["https://dl.example/a.rar"])
+            .await;
+        assert!(
+            matches!(&result, Err(ClickNLoadError::Rejected(text)) if text == "failed"),
+            "{result:?}"
+        );
+
+        assert!(!ClickNLoad::new(closed_endpoint()).is_running().await);
+    }
+}
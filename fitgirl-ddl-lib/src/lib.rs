@@ -3,11 +3,35 @@ use std::sync::{LazyLock, OnceLock};
 use http::HeaderValue;
 use wreq::Client;
 
+#[cfg(feature = "aria2")]
+pub mod aria2;
+pub mod check;
+pub mod client;
+pub mod download;
 pub mod errors;
+pub mod export;
 pub mod extract;
+pub mod hoster;
+#[cfg(feature = "jdownloader")]
+pub mod jdownloader;
+pub mod pipeline;
+pub mod probe;
+mod random;
+pub mod repack;
+pub mod retry;
+mod runtime;
+pub mod schedule;
 pub mod scrape;
+pub mod search;
+pub mod space;
+#[cfg(test)]
+mod testing;
+pub mod torrent;
+pub mod verify;
 
+pub use client::{FitgirlClient, FitgirlClientBuilder};
 pub use http;
+pub use url;
 pub use wreq::{Request, RequestBuilder};
 
 pub static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
@@ -16,12 +40,31 @@ pub static HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
         .build()
         .unwrap()
 });
+
+static DEFAULT_CLIENT: LazyLock<FitgirlClient> = LazyLock::new(FitgirlClient::default);
+
+/// Cookies for fitgirl, read by [default_client] if it has none yet.
+#[deprecated(note = "use `FitgirlClient::set_fitgirl_cookies`")]
 pub static FITGIRL_COOKIES: OnceLock<HeaderValue> = OnceLock::new();
 
+/// The client behind free functions like [scrape::scrape_game] and [extract::extract_ddl].
+pub fn default_client() -> &'static FitgirlClient {
+    #[allow(deprecated)]
+    if let Some(cookies) = FITGIRL_COOKIES.get()
+        && DEFAULT_CLIENT.fitgirl_cookies().is_none()
+    {
+        DEFAULT_CLIENT.set_fitgirl_cookies(cookies.clone());
+    }
+    &DEFAULT_CLIENT
+}
+
 /// Accepts cookies in form like `name1=value1; name=value2; ...`,
 /// Also see [MDN](https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/Cookie).
 ///
-/// Once cookies for fitgirl was initialized, this function returns Err(cookies)
+/// Sets cookies for fitgirl on [default_client], previous cookies will be replaced.
+/// Always succeeds, the result is kept for compatibility.
+#[deprecated(note = "use `default_client().set_fitgirl_cookies`")]
 pub fn set_fg_cookies(cookies: impl Into<HeaderValue>) -> Result<(), HeaderValue> {
-    FITGIRL_COOKIES.set(cookies.into())
+    DEFAULT_CLIENT.set_fitgirl_cookies(cookies);
+    Ok(())
 }
@@ -0,0 +1,609 @@
+use std::path::PathBuf;
+
+use futures_util::StreamExt as _;
+use tracing::{debug, warn};
+
+use super::{EventSender, FetchEvent, extract_links, scrape_games, write_output};
+use crate::FitgirlClient;
+use crate::check::{CompletenessReport, check_completeness};
+use crate::errors::ExtractError;
+use crate::export::{Exporter, export_ddls};
+use crate::extract::DDL;
+use crate::repack::RepackFile;
+use crate::retry::RetryPolicy;
+use crate::scrape::GameInfo;
+use crate::torrent::TorrentInfo;
+
+type ExporterFn<'a> = Box<dyn Fn(&GameReport) -> Box<dyn Exporter + 'a> + 'a>;
+
+/// Scrape → extract → export of games, configured like a builder and started by [FetchJob::run].
+///
+/// Progress is reported to [FetchJob::events] as it goes, the outcome is returned as a [FetchReport].
+pub struct FetchJob<'a> {
+    client: &'a FitgirlClient,
+    retry: RetryPolicy,
+    events: EventSender,
+    game_urls: Vec<String>,
+    pages: Vec<(String, String)>,
+    workers: usize,
+    select: Vec<String>,
+    extract: bool,
+    fetch_torrent: bool,
+    skip_incomplete: bool,
+    probe: bool,
+    exporter: Option<ExporterFn<'a>>,
+    output_dir: PathBuf,
+    file_suffix: String,
+    subdirs: bool,
+}
+
+/// Outcome of a [FetchJob]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct FetchReport {
+    /// Games in the order they were scraped, including skipped ones
+    pub games: Vec<GameReport>,
+    /// Urls and pages which failed scraping, paired with the error
+    pub scrape_errors: Vec<(String, String)>,
+    /// Extraction stopped at a rate limit, later games are not in [Self::games].
+    ///
+    /// The last game keeps the links resolved before, the rate limited ones are in its failed links.
+    pub rate_limited: bool,
+}
+
+impl FetchReport {
+    /// Files deleted from the hoster, of every game
+    pub fn missing_files(&self) -> impl Iterator<Item = &str> {
+        self.games
+            .iter()
+            .flat_map(|game| &game.missing_files)
+            .map(String::as_str)
+    }
+}
+
+/// What a [FetchJob] did with one game
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct GameReport {
+    pub game: GameInfo,
+    /// Resolved links of the selected files, probed if [FetchJob::probe] was set
+    pub ddls: Vec<DDL>,
+    /// Filenames left out by [FetchJob::select]
+    pub unselected: Vec<String>,
+    /// Filenames deleted from the hoster
+    pub missing_files: Vec<String>,
+    /// Links failed for other reasons, paired with the error
+    pub failed_links: Vec<(String, String)>,
+    /// Url and content of the first `.torrent`, if [FetchJob::fetch_torrent] was set
+    pub torrent: Option<(String, TorrentInfo)>,
+    /// Parts compared with `torrent`
+    pub completeness: Option<CompletenessReport>,
+    /// Not extracted because of incomplete parts, see [FetchJob::skip_incomplete]
+    pub skipped: bool,
+    /// The written export
+    pub output: Option<PathBuf>,
+    pub write_error: Option<String>,
+}
+
+impl<'a> FetchJob<'a> {
+    /// Job of `client` without games, nothing is written until an exporter is set
+    pub fn new(client: &'a FitgirlClient) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+            events: EventSender::none(),
+            game_urls: Vec::new(),
+            pages: Vec::new(),
+            workers: client.scheduler().config().max_concurrency,
+            select: Vec::new(),
+            extract: true,
+            fetch_torrent: false,
+            skip_incomplete: false,
+            probe: false,
+            exporter: None,
+            output_dir: PathBuf::from("."),
+            file_suffix: String::new(),
+            subdirs: false,
+        }
+    }
+
+    /// Game pages to scrape
+    pub fn urls(mut self, urls: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.game_urls.extend(urls.into_iter().map(Into::into));
+        self
+    }
+
+    /// A saved game page, `source` names it in the report
+    pub fn page(mut self, source: impl Into<String>, html: impl Into<String>) -> Self {
+        self.pages.push((source.into(), html.into()));
+        self
+    }
+
+    /// Pages scraped, links extracted and probed at a time,
+    /// defaults to `max_concurrency` of the client's scheduler
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Report progress to the stream of `events`
+    pub fn events(mut self, events: EventSender) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Optional groups to download like `fg-selective-english`, other optional files are left out.
+    ///
+    /// All files are kept if no group is selected.
+    pub fn select(mut self, groups: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.select.extend(groups.into_iter().map(Into::into));
+        self
+    }
+
+    /// Resolve direct links, defaults to `true`; without it games are only scraped
+    pub fn extract(mut self, extract: bool) -> Self {
+        self.extract = extract;
+        self
+    }
+
+    /// Fetch the first `.torrent` of each game and check its parts against it
+    pub fn fetch_torrent(mut self, fetch_torrent: bool) -> Self {
+        self.fetch_torrent = fetch_torrent;
+        self
+    }
+
+    /// Skip games whose parts differ from the torrent, implies [Self::fetch_torrent]
+    pub fn skip_incomplete(mut self, skip_incomplete: bool) -> Self {
+        self.skip_incomplete = skip_incomplete;
+        self.fetch_torrent |= skip_incomplete;
+        self
+    }
+
+    /// Probe resolved links for their size and liveness
+    pub fn probe(mut self, probe: bool) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Write every game with `exporter`
+    pub fn exporter(self, exporter: impl Exporter + Clone + 'a) -> Self {
+        self.exporter_with(move |_| Box::new(exporter.clone()))
+    }
+
+    /// Pick the exporter of each game, e.g. to add its torrent as a source
+    pub fn exporter_with(
+        mut self,
+        exporter: impl Fn(&GameReport) -> Box<dyn Exporter + 'a> + 'a,
+    ) -> Self {
+        self.exporter = Some(Box::new(exporter));
+        self
+    }
+
+    /// Directory of the exports, defaults to the current one
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    /// Exports are named `{path_part}{suffix}.{extension}`
+    pub fn file_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.file_suffix = suffix.into();
+        self
+    }
+
+    /// Download files of each game into its `path_part` directory
+    pub fn subdirs(mut self, subdirs: bool) -> Self {
+        self.subdirs = subdirs;
+        self
+    }
+
+    pub async fn run(self) -> FetchReport {
+        let mut report = FetchReport::default();
+
+        let mut games = Vec::new();
+        let scraped = scrape_games(
+            self.client,
+            &self.retry,
+            self.game_urls.iter().cloned(),
+            self.workers,
+            &self.events,
+        )
+        .await;
+        let parsed = self.pages.iter().map(|(source, html)| {
+            let result = self.client.parse_game_page(html, None);
+            self.events.send(match &result {
+                Ok(game) => FetchEvent::ScrapeFinished {
+                    url: source.clone(),
+                    path_part: game.path_part.clone(),
+                    links: game.link_count(),
+                },
+                Err(e) => FetchEvent::ScrapeFailed {
+                    url: source.clone(),
+                    error: e.to_string(),
+                },
+            });
+            (source.clone(), result)
+        });
+        for (source, result) in scraped.into_iter().chain(parsed) {
+            match result {
+                Ok(game) => games.push(game),
+                Err(e) => report.scrape_errors.push((source, e.to_string())),
+            }
+        }
+
+        let selected: Vec<_> = games
+            .iter()
+            .map(|game| self.select_links(game.mirror_links(self.client.hosters())))
+            .collect();
+        self.events.send(FetchEvent::ExtractPlanned {
+            games: games.len(),
+            links: selected.iter().map(|(links, _)| links.len()).sum(),
+        });
+
+        for (game, (links, unselected)) in games.into_iter().zip(selected) {
+            let mut game_report = GameReport {
+                game,
+                ddls: Vec::new(),
+                unselected,
+                missing_files: Vec::new(),
+                failed_links: Vec::new(),
+                torrent: None,
+                completeness: None,
+                skipped: false,
+                output: None,
+                write_error: None,
+            };
+            let path_part = game_report.game.path_part.clone();
+
+            if self.fetch_torrent {
+                self.check_torrent(&mut game_report).await;
+                if self.skip_incomplete
+                    && game_report
+                        .completeness
+                        .as_ref()
+                        .is_some_and(CompletenessReport::is_incomplete)
+                {
+                    game_report.skipped = true;
+                    report.games.push(game_report);
+                    continue;
+                }
+            }
+
+            if self.extract {
+                let extracted = extract_links(
+                    self.client,
+                    &self.retry,
+                    &path_part,
+                    links,
+                    self.workers,
+                    &self.events,
+                )
+                .await;
+                game_report.ddls = extracted.ddls;
+                game_report.missing_files = extracted.missing_files;
+                game_report.failed_links = extracted.failed_links;
+                if !extracted.rate_limited.is_empty() {
+                    warn!("{path_part} is rate limited, later games are skipped");
+                    report.rate_limited = true;
+                    game_report.failed_links.extend(
+                        extracted
+                            .rate_limited
+                            .into_iter()
+                            .map(|url| (url, ExtractError::RateLimited.to_string())),
+                    );
+                }
+            }
+
+            if self.probe {
+                self.probe_ddls(&mut game_report.ddls).await;
+            }
+
+            if let Some(exporter) = &self.exporter {
+                let exporter = exporter(&game_report);
+                let dir = self.subdirs.then_some(path_part.as_str());
+                let output = export_ddls(&*exporter, &game_report.ddls, dir);
+                let path = self.output_dir.join(format!(
+                    "{path_part}{}.{}",
+                    self.file_suffix,
+                    exporter.extension()
+                ));
+                match write_output(&path_part, &path, output, &self.events).await {
+                    Ok(_) => game_report.output = Some(path),
+                    Err(e) => game_report.write_error = Some(e.to_string()),
+                }
+            }
+
+            report.games.push(game_report);
+            if report.rate_limited {
+                break;
+            }
+        }
+
+        report
+    }
+
+    /// Links of selected files, and filenames of the others
+    fn select_links(&self, links: Vec<String>) -> (Vec<String>, Vec<String>) {
+        if !self.extract {
+            return (Vec::new(), Vec::new());
+        }
+        if self.select.is_empty() {
+            return (links, Vec::new());
+        }
+
+        let mut selected = Vec::new();
+        let mut unselected = Vec::new();
+        for link in links {
+            let filename = link.split('#').nth(1).unwrap_or_default();
+            if RepackFile::parse(filename).is_selected(self.select.iter().map(String::as_str)) {
+                selected.push(link);
+            } else {
+                unselected.push(filename.to_string());
+            }
+        }
+        (selected, unselected)
+    }
+
+    async fn check_torrent(&self, game_report: &mut GameReport) {
+        let game = &game_report.game;
+        let Some(torrent_url) = game.torrent_files.first() else {
+            debug!("no .torrent for {}", game.path_part);
+            return;
+        };
+        match self.client.fetch_torrent(torrent_url).await {
+            Ok(info) => {
+                let completeness = check_completeness(&game.fuckingfast_links, &info);
+                self.events.send(FetchEvent::PartsChecked {
+                    path_part: game.path_part.clone(),
+                    report: completeness.clone(),
+                });
+                game_report.completeness = Some(completeness);
+                game_report.torrent = Some((torrent_url.clone(), info));
+            }
+            Err(e) => warn!("failed to fetch {torrent_url}: {e}"),
+        }
+    }
+
+    async fn probe_ddls(&self, ddls: &mut [DDL]) {
+        futures_util::stream::iter(ddls.iter_mut())
+            .for_each_concurrent(self.workers, |ddl| async move {
+                if let Err(e) = self.client.probe_ddl(ddl).await.map(|_| ()) {
+                    warn!("failed to probe {}: {e}", ddl.filename);
+                }
+            })
+            .await;
+    }
+}
+
+#[cfg(all(test, feature = "compio"))]
+mod tests {
+    use std::time::Duration;
+
+    use scraper::{Html, Selector};
+
+    use super::*;
+    use crate::errors::ScrapeError;
+    use crate::export::Aria2;
+    use crate::hoster::{BoxFuture, Hoster};
+    use crate::schedule::{Scheduler, SchedulerConfig};
+    use crate::testing::{TempDir, ddl};
+
+    /// Hoster of links under `https://{name}.test/`, resolves them by their filename
+    struct StubHoster(&'static str);
+
+    impl Hoster for StubHoster {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn owns_link(&self, url: &str) -> bool {
+            url.strip_prefix("https://")
+                .and_then(|url| url.strip_prefix(self.0))
+                .is_some_and(|url| url.starts_with(".test/"))
+        }
+
+        fn discover_links(&self, document: &Html) -> Result<Vec<String>, ScrapeError> {
+            let link_selector = Selector::parse("div.entry-content a[href]")?;
+            Ok(document
+                .select(&link_selector)
+                .filter_map(|tag| tag.attr("href"))
+                .filter(|href| self.owns_link(href))
+                .map(str::to_string)
+                .collect())
+        }
+
+        fn resolve_ddl<'a>(
+            &'a self,
+            _client: &'a FitgirlClient,
+            url: &'a str,
+        ) -> BoxFuture<'a, Result<DDL, ExtractError>> {
+            Box::pin(async move {
+                let filename = url.split('#').nth(1).unwrap_or_default().to_string();
+                match filename.as_str() {
+                    "missing.rar" => Err(ExtractError::FileNotFound(filename)),
+                    "limited.rar" => Err(ExtractError::RateLimited),
+                    _ => Ok(DDL {
+                        source_url: url.to_string(),
+                        ..ddl(&filename)
+                    }),
+                }
+            })
+        }
+    }
+
+    fn client() -> FitgirlClient {
+        // no cooldown, the rate limited link would hold up the others
+        let scheduler = Scheduler::new(SchedulerConfig {
+            rate: 1000.0,
+            burst: 100,
+            cooldown: Duration::ZERO,
+            ..Default::default()
+        });
+        FitgirlClient::builder()
+            .hoster(StubHoster("stub"))
+            .hoster(StubHoster("mirror"))
+            .scheduler(scheduler)
+            .build()
+    }
+
+    fn page(path_part: &str, filenames: &[&str]) -> String {
+        mirrors_page(path_part, &[("stub", filenames)])
+    }
+
+    /// Page listing `filenames` on each hoster
+    fn mirrors_page(path_part: &str, mirrors: &[(&str, &[&str])]) -> String {
+        let links: String = mirrors
+            .iter()
+            .flat_map(|(hoster, filenames)| {
+                filenames.iter().map(move |filename| {
+                    format!(
+                        r#"<a href="https://{hoster}.test/{path_part}#{filename}">{filename}</a>"#
+                    )
+                })
+            })
+            .collect();
+        format!(
+            r#"<html><head><link rel="canonical" href="https://fitgirl-repacks.site/{path_part}/"></head>
+            <body><h1 class="entry-title">{path_part}</h1><div class="entry-content">{links}</div></body></html>"#
+        )
+    }
+
+    fn filenames(ddls: &[DDL]) -> Vec<&str> {
+        let mut filenames: Vec<_> = ddls.iter().map(|ddl| ddl.filename.as_str()).collect();
+        filenames.sort();
+        filenames
+    }
+
+    #[compio::test]
+    async fn extracts_and_exports_with_registered_hoster() {
+        let dir = TempDir::new();
+        let client = client();
+
+        let report = FetchJob::new(&client)
+            .retry(RetryPolicy::none())
+            .page("one.html", page("game-one", &["part1.rar", "missing.rar"]))
+            .page("two.html", page("game-two", &["part1.rar", "part2.rar"]))
+            .page("broken.html", "<html></html>")
+            .exporter(Aria2)
+            .output_dir(&*dir)
+            .file_suffix("_full")
+            .run()
+            .await;
+
+        assert!(!report.rate_limited);
+        assert_eq!(report.scrape_errors.len(), 1);
+        assert_eq!(report.scrape_errors[0].0, "broken.html");
+        assert_eq!(report.games.len(), 2);
+        assert_eq!(report.missing_files().collect::<Vec<_>>(), ["missing.rar"]);
+
+        let game = &report.games[1];
+        assert_eq!(game.game.path_part, "game-two");
+        assert_eq!(filenames(&game.ddls), ["part1.rar", "part2.rar"]);
+        assert_eq!(game.output, Some(dir.join("game-two_full.txt")));
+        let export = std::fs::read_to_string(dir.join("game-two_full.txt")).unwrap();
+        assert!(export.contains("https://dl.example/part2.rar"));
+    }
+
+    #[compio::test]
+    async fn resolves_each_file_from_first_hoster_listing_it() {
+        let client = client();
+
+        let report = FetchJob::new(&client)
+            .retry(RetryPolicy::none())
+            .page(
+                "one.html",
+                mirrors_page(
+                    "game-one",
+                    &[
+                        ("mirror", &["part1.rar", "part2.rar"]),
+                        ("stub", &["part1.rar"]),
+                    ],
+                ),
+            )
+            .run()
+            .await;
+
+        let game = &report.games[0];
+        assert_eq!(game.game.link_count(), 3);
+        let mut sources: Vec<_> = game
+            .ddls
+            .iter()
+            .map(|ddl| ddl.source_url.as_str())
+            .collect();
+        sources.sort();
+        assert_eq!(
+            sources,
+            [
+                "https://mirror.test/game-one#part2.rar",
+                "https://stub.test/game-one#part1.rar",
+            ]
+        );
+    }
+
+    #[compio::test]
+    async fn keeps_resolved_links_when_rate_limited() {
+        let client = client();
+
+        let report = FetchJob::new(&client)
+            .retry(RetryPolicy::none())
+            .page(
+                "one.html",
+                page(
+                    "game-one",
+                    &["part1.rar", "limited.rar", "part2.rar", "missing.rar"],
+                ),
+            )
+            .page("two.html", page("game-two", &["part1.rar"]))
+            .run()
+            .await;
+
+        assert!(report.rate_limited);
+        assert_eq!(report.games.len(), 1);
+
+        let game = &report.games[0];
+        assert_eq!(game.game.path_part, "game-one");
+        assert_eq!(filenames(&game.ddls), ["part1.rar", "part2.rar"]);
+        assert_eq!(game.missing_files, ["missing.rar"]);
+        assert_eq!(
+            game.failed_links,
+            [(
+                "https://stub.test/game-one#limited.rar".to_string(),
+                ExtractError::RateLimited.to_string()
+            )]
+        );
+    }
+
+    #[compio::test]
+    async fn select_leaves_out_other_optional_files() {
+        let client = client();
+
+        let report = FetchJob::new(&client)
+            .retry(RetryPolicy::none())
+            .select(["fg-selective-english"])
+            .page(
+                "one.html",
+                page(
+                    "game-one",
+                    &[
+                        "fg-01.bin",
+                        "fg-selective-english.bin",
+                        "fg-selective-french.bin",
+                    ],
+                ),
+            )
+            .run()
+            .await;
+
+        let game = &report.games[0];
+        assert_eq!(
+            filenames(&game.ddls),
+            ["fg-01.bin", "fg-selective-english.bin"]
+        );
+        assert_eq!(game.unselected, ["fg-selective-french.bin"]);
+    }
+}
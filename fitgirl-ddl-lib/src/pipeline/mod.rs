@@ -0,0 +1,231 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender, unbounded};
+use futures_util::StreamExt as _;
+
+#[cfg(feature = "compio")]
+use compio::runtime::spawn_blocking;
+#[cfg(feature = "tokio")]
+use tokio::task::spawn_blocking;
+
+use crate::FitgirlClient;
+use crate::check::CompletenessReport;
+use crate::errors::{ExtractError, ScrapeError};
+use crate::extract::DDL;
+use crate::retry::RetryPolicy;
+use crate::scrape::GameInfo;
+
+mod job;
+
+pub use job::{FetchJob, FetchReport, GameReport};
+
+/// Progress of the scrape → extract → write steps, for front-ends to display
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub enum FetchEvent {
+    ScrapeStarted {
+        url: String,
+    },
+    /// `links` links of all hosters were found on the page
+    ScrapeFinished {
+        url: String,
+        path_part: String,
+        links: usize,
+    },
+    ScrapeFailed {
+        url: String,
+        error: String,
+    },
+    /// Links of all scraped games are about to be extracted
+    ExtractPlanned {
+        games: usize,
+        links: usize,
+    },
+    /// Parts were compared with the torrent file list
+    PartsChecked {
+        path_part: String,
+        report: CompletenessReport,
+    },
+    ExtractStarted {
+        path_part: String,
+        links: usize,
+    },
+    LinkResolved {
+        url: String,
+        ddl: DDL,
+    },
+    LinkFailed {
+        url: String,
+        error: String,
+    },
+    /// The file was deleted from the hoster
+    FileMissing {
+        url: String,
+        filename: String,
+    },
+    /// Still rate limited after retrying, extraction stops
+    RateLimited {
+        url: String,
+    },
+    GameWritten {
+        path_part: String,
+        path: PathBuf,
+    },
+}
+
+impl FetchEvent {
+    /// Whether a link is done with, resolved or not
+    pub fn is_link_done(&self) -> bool {
+        matches!(
+            self,
+            Self::LinkResolved { .. }
+                | Self::LinkFailed { .. }
+                | Self::FileMissing { .. }
+                | Self::RateLimited { .. }
+        )
+    }
+}
+
+/// Events in the order they happened, ends once every [EventSender] is dropped
+pub type EventStream = UnboundedReceiver<FetchEvent>;
+
+/// Sending half of [EventStream], events are dropped if nobody listens.
+#[derive(Debug, Clone, Default)]
+pub struct EventSender(Option<UnboundedSender<FetchEvent>>);
+
+impl EventSender {
+    /// Sender which drops every event
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn send(&self, event: FetchEvent) {
+        if let Some(sender) = &self.0 {
+            // the receiver was dropped, nobody is interested anymore
+            _ = sender.unbounded_send(event);
+        }
+    }
+}
+
+/// A connected pair of [EventSender] and [EventStream]
+pub fn channel() -> (EventSender, EventStream) {
+    let (sender, receiver) = unbounded();
+    (EventSender(Some(sender)), receiver)
+}
+
+/// Links of a game resolved by [extract_links]
+#[derive(Debug, Clone, Default)]
+pub struct ExtractedLinks {
+    pub ddls: Vec<DDL>,
+    /// Filenames deleted from the hoster
+    pub missing_files: Vec<String>,
+    /// Links failed for other reasons, paired with the error
+    pub failed_links: Vec<(String, String)>,
+    /// Links still rate limited after retrying
+    pub rate_limited: Vec<String>,
+}
+
+/// Scrape `game_urls` with `workers` pages at a time, results are paired with their url
+pub async fn scrape_games(
+    client: &FitgirlClient,
+    retry: &RetryPolicy,
+    game_urls: impl IntoIterator<Item = String>,
+    workers: usize,
+    events: &EventSender,
+) -> Vec<(String, Result<GameInfo, ScrapeError>)> {
+    futures_util::stream::iter(game_urls)
+        .map(|url| async move {
+            events.send(FetchEvent::ScrapeStarted { url: url.clone() });
+            let result = retry.run(async || client.scrape_game(&url).await).await;
+            events.send(match &result {
+                Ok(game) => FetchEvent::ScrapeFinished {
+                    url: url.clone(),
+                    path_part: game.path_part.clone(),
+                    links: game.link_count(),
+                },
+                Err(e) => FetchEvent::ScrapeFailed {
+                    url: url.clone(),
+                    error: e.to_string(),
+                },
+            });
+            (url, result)
+        })
+        .buffer_unordered(workers)
+        .collect()
+        .await
+}
+
+/// Extract `links` of the game `path_part` with the hoster serving each of them,
+/// failed links are collected and skipped.
+///
+/// Links still rate limited after retrying end up in [ExtractedLinks::rate_limited],
+/// callers should stop extracting more.
+pub async fn extract_links(
+    client: &FitgirlClient,
+    retry: &RetryPolicy,
+    path_part: &str,
+    links: Vec<String>,
+    workers: usize,
+    events: &EventSender,
+) -> ExtractedLinks {
+    events.send(FetchEvent::ExtractStarted {
+        path_part: path_part.to_string(),
+        links: links.len(),
+    });
+
+    let results: Vec<_> = futures_util::stream::iter(links)
+        .map(|url| async move {
+            let result = retry.run(async || client.resolve_ddl(&url).await).await;
+            events.send(match &result {
+                Ok(ddl) => FetchEvent::LinkResolved {
+                    url: url.clone(),
+                    ddl: ddl.clone(),
+                },
+                Err(ExtractError::FileNotFound(filename)) => FetchEvent::FileMissing {
+                    url: url.clone(),
+                    filename: filename.clone(),
+                },
+                Err(ExtractError::RateLimited) => FetchEvent::RateLimited { url: url.clone() },
+                Err(e) => FetchEvent::LinkFailed {
+                    url: url.clone(),
+                    error: e.to_string(),
+                },
+            });
+            (url, result)
+        })
+        .buffer_unordered(workers)
+        .collect()
+        .await;
+
+    let mut extracted = ExtractedLinks::default();
+    for (url, result) in results {
+        match result {
+            Ok(ddl) => extracted.ddls.push(ddl),
+            Err(ExtractError::RateLimited) => extracted.rate_limited.push(url),
+            Err(ExtractError::FileNotFound(filename)) => extracted.missing_files.push(filename),
+            Err(e) => extracted.failed_links.push((url, e.to_string())),
+        }
+    }
+    extracted
+}
+
+/// Save the export of the game `path_part` to `path`
+pub async fn write_output(
+    path_part: &str,
+    path: impl AsRef<Path>,
+    contents: String,
+    events: &EventSender,
+) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let target = path.clone();
+    spawn_blocking(move || std::fs::write(target, contents))
+        .await
+        .map_err(|_| io::Error::other("join error"))??;
+
+    events.send(FetchEvent::GameWritten {
+        path_part: path_part.to_string(),
+        path,
+    });
+    Ok(())
+}
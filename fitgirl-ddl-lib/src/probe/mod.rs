@@ -0,0 +1,228 @@
+use http::header::{
+    ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE,
+};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use tracing::debug;
+
+use crate::errors::ExtractError;
+use crate::extract::DDL;
+use crate::schedule::host_key;
+use crate::{FitgirlClient, default_client};
+
+/// Response headers of a direct link, without downloading its body
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProbeInfo {
+    pub status: u16,
+    /// Size of the whole file
+    pub content_length: Option<u64>,
+    pub accept_ranges: bool,
+    pub content_type: Option<String>,
+    /// Filename from `Content-Disposition`
+    pub filename: Option<String>,
+}
+
+impl ProbeInfo {
+    /// Whether the link serves a file, dead links usually answer with an error page
+    pub fn is_alive(&self) -> bool {
+        StatusCode::from_u16(self.status).is_ok_and(|status| status.is_success())
+            && !is_html(self.content_type.as_deref())
+    }
+
+    /// Whether the link is dead in a way [FitgirlClient::refresh_ddl] may fix
+    pub fn looks_expired(&self) -> bool {
+        looks_expired(self.status, self.content_type.as_deref())
+    }
+}
+
+/// Expired direct links are answered with 403, 410 or an HTML page in place of the file
+pub(crate) fn looks_expired(status: u16, content_type: Option<&str>) -> bool {
+    matches!(
+        StatusCode::from_u16(status),
+        Ok(StatusCode::FORBIDDEN | StatusCode::GONE)
+    ) || is_html(content_type)
+}
+
+fn is_html(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|content_type| content_type.starts_with("text/html"))
+}
+
+/// Probe with [default_client].
+pub async fn probe(direct_link: impl AsRef<str>) -> Result<ProbeInfo, ExtractError> {
+    default_client().probe(direct_link).await
+}
+
+/// Sum of probed sizes, `None` if any of `ddls` is not probed or has unknown size
+pub fn total_size<'a>(ddls: impl IntoIterator<Item = &'a DDL>) -> Option<u64> {
+    ddls.into_iter()
+        .map(|ddl| ddl.probe.as_ref()?.content_length)
+        .sum()
+}
+
+impl FitgirlClient {
+    /// Send a HEAD request to `direct_link`, or a GET of the first byte if HEAD tells nothing.
+    pub async fn probe(&self, direct_link: impl AsRef<str>) -> Result<ProbeInfo, ExtractError> {
+        let direct_link = direct_link.as_ref();
+        self.scheduler()
+            .run(&host_key(direct_link), async || {
+                let info = self.probe_head(direct_link).await;
+                match info {
+                    Ok(info) if info.is_alive() && info.content_length.is_some() => Ok(info),
+                    _ => {
+                        debug!("HEAD of {direct_link} inconclusive, trying ranged GET");
+                        self.probe_range(direct_link).await
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Probe `ddl.direct_link` and store the result into `ddl.probe`.
+    pub async fn probe_ddl<'a>(&self, ddl: &'a mut DDL) -> Result<&'a ProbeInfo, ExtractError> {
+        let info = self.probe(&ddl.direct_link).await?;
+        Ok(ddl.probe.insert(info))
+    }
+
+    async fn probe_head(&self, direct_link: &str) -> Result<ProbeInfo, ExtractError> {
+        let resp = self
+            .http()
+            .head(direct_link)
+            .send()
+            .await
+            .map_err(|e| ExtractError::RequestError(e.to_string()))?;
+
+        let headers = resp.headers();
+        Ok(ProbeInfo {
+            status: resp.status().as_u16(),
+            content_length: header_str(headers, CONTENT_LENGTH).and_then(|len| len.parse().ok()),
+            accept_ranges: header_str(headers, ACCEPT_RANGES).is_some_and(|v| v.contains("bytes")),
+            content_type: header_str(headers, CONTENT_TYPE).map(str::to_string),
+            filename: header_str(headers, CONTENT_DISPOSITION).and_then(disposition_filename),
+        })
+    }
+
+    async fn probe_range(&self, direct_link: &str) -> Result<ProbeInfo, ExtractError> {
+        let resp = self
+            .http()
+            .get(direct_link)
+            .header(RANGE, HeaderValue::from_static("bytes=0-0"))
+            .send()
+            .await
+            .map_err(|e| ExtractError::RequestError(e.to_string()))?;
+
+        let status = resp.status();
+        let headers = resp.headers();
+        let content_length = if status == StatusCode::PARTIAL_CONTENT {
+            // bytes 0-0/12345
+            header_str(headers, CONTENT_RANGE)
+                .and_then(|range| range.rsplit_once('/'))
+                .and_then(|(_, total)| total.trim().parse().ok())
+        } else {
+            header_str(headers, CONTENT_LENGTH).and_then(|len| len.parse().ok())
+        };
+
+        // the body is dropped unread, at most one byte when the range is honored
+        Ok(ProbeInfo {
+            status: status.as_u16(),
+            content_length,
+            accept_ranges: status == StatusCode::PARTIAL_CONTENT,
+            content_type: header_str(headers, CONTENT_TYPE).map(str::to_string),
+            filename: header_str(headers, CONTENT_DISPOSITION).and_then(disposition_filename),
+        })
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: http::header::HeaderName) -> Option<&str> {
+    headers.get(name)?.to_str().ok()
+}
+
+/// `attachment; filename="a.rar"` or `attachment; filename*=UTF-8''a%20b.rar`,
+/// the plain `filename` is used if `filename*` can't be decoded
+fn disposition_filename(disposition: &str) -> Option<String> {
+    let mut extended = None;
+    let mut plain = None;
+    for param in disposition.split(';').map(str::trim) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                extended = value
+                    .trim()
+                    .split_once("''")
+                    .and_then(|(_, encoded)| percent_decode(encoded));
+            }
+            "filename" => plain = Some(value.trim().trim_matches('"').to_string()),
+            _ => (),
+        }
+    }
+    extended
+        .filter(|name| !name.is_empty())
+        .or(plain.filter(|name| !name.is_empty()))
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = encoded.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_disposition_filename() {
+        assert_eq!(
+            disposition_filename(r#"attachment; filename="a b.rar""#).as_deref(),
+            Some("a b.rar")
+        );
+        assert_eq!(
+            disposition_filename("attachment; filename*=UTF-8''%C3%A9t%C3%A9.rar").as_deref(),
+            Some("été.rar")
+        );
+        // filename* is preferred, in either order
+        assert_eq!(
+            disposition_filename(r#"attachment; filename*=UTF-8''b.rar; filename="a.rar""#)
+                .as_deref(),
+            Some("b.rar")
+        );
+        assert_eq!(disposition_filename("attachment"), None);
+        assert_eq!(disposition_filename(r#"attachment; filename="""#), None);
+    }
+
+    #[test]
+    fn falls_back_to_plain_filename() {
+        for extended in ["%ZZ.rar", "UTF-8''%E9.rar", "UTF-8''%4", "no-charset.rar"] {
+            let disposition = format!(r#"attachment; filename="a.rar"; filename*={extended}"#);
+            assert_eq!(
+                disposition_filename(&disposition).as_deref(),
+                Some("a.rar"),
+                "{disposition}"
+            );
+        }
+        assert_eq!(
+            disposition_filename("attachment; filename*=UTF-8''%ZZ"),
+            None
+        );
+    }
+
+    #[test]
+    fn percent_decodes() {
+        assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+        assert_eq!(percent_decode("100%"), None);
+        assert_eq!(percent_decode("%C3"), None);
+        assert_eq!(percent_decode("%é1"), None);
+    }
+}
@@ -0,0 +1,42 @@
+//! Cheap random numbers for jitter and one-off keys, not for cryptography
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Random `u64`, seeded by the clock and differing between calls
+pub(crate) fn random_u64() -> u64 {
+    static STATE: AtomicU64 = AtomicU64::new(0);
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    // splitmix64
+    let mut z = STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed) ^ seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Random number in `0.0..1.0`
+pub(crate) fn random_unit() -> f64 {
+    (random_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_is_in_range_and_varies() {
+        let samples: Vec<_> = (0..1000).map(|_| random_unit()).collect();
+        assert!(samples.iter().all(|x| (0.0..1.0).contains(x)));
+        assert!(samples.iter().any(|&x| x < 0.5));
+        assert!(samples.iter().any(|&x| x >= 0.5));
+    }
+
+    #[test]
+    fn consecutive_calls_differ() {
+        assert_ne!(random_u64(), random_u64());
+    }
+}
@@ -0,0 +1,349 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// What a file of a repack is used for
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RepackKind {
+    /// Archives or `fg-NN.bin` of the game itself
+    Main,
+    /// `fg-optional-*`, bonus content, videos, etc.
+    Optional,
+    /// `fg-selective-*`, usually languages
+    Selective,
+    Setup,
+    Md5,
+    Redist,
+    Other,
+}
+
+impl RepackKind {
+    /// `fg-optional-*` and `fg-selective-*`, which the installer can do without
+    pub fn is_optional(self) -> bool {
+        matches!(self, Self::Optional | Self::Selective)
+    }
+
+    /// Files every install needs
+    pub fn is_required(self) -> bool {
+        matches!(self, Self::Main | Self::Setup | Self::Md5 | Self::Redist)
+    }
+}
+
+/// A fragment filename of a fuckingfast link or a file of a torrent, classified
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepackFile {
+    pub filename: String,
+    pub kind: RepackKind,
+    /// Filename without part number and extension, shared by all parts
+    pub group: String,
+    pub part: Option<u32>,
+}
+
+impl RepackFile {
+    /// `Game_--_fitgirl-repacks.site_--_.part01.rar` => group `Game_--_fitgirl-repacks.site_--_`, part 1
+    pub fn parse(filename: &str) -> Self {
+        let (dir, name) = filename
+            .rsplit_once(['/', '\\'])
+            .map(|(dir, name)| (Some(dir), name))
+            .unwrap_or((None, filename));
+        let (group, part) = split_part(name);
+        let lower = group.to_ascii_lowercase();
+
+        let kind = if dir.is_some_and(|dir| dir.eq_ignore_ascii_case("md5"))
+            || extension(name).is_some_and(|ext| ext.eq_ignore_ascii_case("md5"))
+        {
+            RepackKind::Md5
+        } else if lower.starts_with("fg-optional") {
+            RepackKind::Optional
+        } else if lower.starts_with("fg-selective") {
+            RepackKind::Selective
+        } else if lower.starts_with("setup") {
+            RepackKind::Setup
+        } else if ["redist", "directx"].iter().any(|key| lower.contains(key)) {
+            RepackKind::Redist
+        } else if lower == "fg"
+            || ["fitgirl-repacks.site", "fixed"]
+                .iter()
+                .any(|key| lower.contains(key))
+        {
+            RepackKind::Main
+        } else {
+            RepackKind::Other
+        };
+
+        Self {
+            filename: filename.to_string(),
+            kind,
+            group: group.to_string(),
+            part,
+        }
+    }
+
+    /// Whether to download this file for the chosen optional `groups`,
+    /// files other than [RepackKind::is_optional] are always selected.
+    pub fn is_selected<'a>(&self, groups: impl IntoIterator<Item = &'a str>) -> bool {
+        !self.kind.is_optional()
+            || groups
+                .into_iter()
+                .any(|group| Self::parse(group).group == self.group)
+    }
+
+    /// Readable name of an optional group, `fg-selective-french` => `French language`,
+    /// other files keep their group name.
+    pub fn label(&self) -> String {
+        let lower = self.group.to_ascii_lowercase();
+        let (name, suffix) = match self.kind {
+            RepackKind::Selective => (lower.trim_start_matches("fg-selective"), " language"),
+            RepackKind::Optional => (lower.trim_start_matches("fg-optional"), ""),
+            _ => return self.group.clone(),
+        };
+
+        let words = name
+            .split(['-', '_'])
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut chars = words.chars();
+        match chars.next() {
+            Some(first) => format!("{}{}{suffix}", first.to_uppercase(), chars.as_str()),
+            None => self.group.clone(),
+        }
+    }
+}
+
+/// Group items by [RepackFile::group] of their filename, parts in natural order.
+pub fn group_files<T>(
+    items: impl IntoIterator<Item = T>,
+    filename: impl Fn(&T) -> &str,
+) -> BTreeMap<String, Vec<T>> {
+    let mut groups: BTreeMap<String, Vec<(RepackFile, T)>> = BTreeMap::new();
+    for item in items {
+        let file = RepackFile::parse(filename(&item));
+        groups
+            .entry(file.group.clone())
+            .or_default()
+            .push((file, item));
+    }
+
+    groups
+        .into_iter()
+        .map(|(group, mut files)| {
+            files.sort_by(|(a, _), (b, _)| {
+                a.part
+                    .cmp(&b.part)
+                    .then_with(|| natural_cmp(&a.filename, &b.filename))
+            });
+            (group, files.into_iter().map(|(_, item)| item).collect())
+        })
+        .collect()
+}
+
+/// Compare with digit runs as numbers, so `part9` goes before `part10`.
+///
+/// Names only differing in leading zeros are ordered as plain strings.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_cmp_digits(a, b).then_with(|| a.cmp(b))
+}
+
+fn natural_cmp_digits(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (na, ra) = a.split_at(a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len()));
+            let (nb, rb) = b.split_at(b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len()));
+            let (ta, tb) = (na.trim_start_matches('0'), nb.trim_start_matches('0'));
+            let ordering = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (ra, rb);
+        } else {
+            if ca != cb {
+                return ca.cmp(&cb);
+            }
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+        }
+    }
+}
+
+/// Short alphanumeric suffix after the last dot, `.site_--_` is not an extension
+fn extension(name: &str) -> Option<&str> {
+    let (_, ext) = name.rsplit_once('.')?;
+    (1..=4)
+        .contains(&ext.len())
+        .then_some(ext)
+        .filter(|ext| ext.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+fn strip_extension(name: &str) -> &str {
+    match extension(name) {
+        Some(ext) => &name[..name.len() - ext.len() - 1],
+        None => name,
+    }
+}
+
+fn parse_number(digits: &str) -> Option<u32> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Split `name.part01.rar`, `name.7z.001` and `fg-01.bin` into group name and part number
+fn split_part(name: &str) -> (&str, Option<u32>) {
+    // name.7z.001
+    if let Some(ext) = extension(name)
+        && let Some(part) = parse_number(ext)
+    {
+        return (strip_extension(strip_extension(name)), Some(part));
+    }
+
+    let stem = strip_extension(name);
+    // name.part01.rar
+    if let Some((group, part)) = stem.rsplit_once(".part")
+        && let Some(part) = parse_number(part)
+    {
+        return (group, Some(part));
+    }
+    // fg-01.bin
+    if let Some((group, part)) = stem.rsplit_once(['-', '_'])
+        && let Some(part) = parse_number(part)
+    {
+        return (group, Some(part));
+    }
+
+    (stem, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(filename: &str) -> (RepackKind, &str, Option<u32>) {
+        let file = RepackFile::parse(filename);
+        let group = &filename[..file.group.len()];
+        assert_eq!(group, file.group, "{filename}");
+        (file.kind, group, file.part)
+    }
+
+    #[test]
+    fn parses_part_names() {
+        assert_eq!(
+            parse("Game_--_fitgirl-repacks.site_--_.part01.rar"),
+            (
+                RepackKind::Main,
+                "Game_--_fitgirl-repacks.site_--_",
+                Some(1)
+            )
+        );
+        assert_eq!(
+            parse("Game_--_fitgirl-repacks.site_--_.7z.012"),
+            (
+                RepackKind::Main,
+                "Game_--_fitgirl-repacks.site_--_",
+                Some(12)
+            )
+        );
+        assert_eq!(parse("fg-02.bin"), (RepackKind::Main, "fg", Some(2)));
+        assert_eq!(
+            parse("fg-selective-french.bin"),
+            (RepackKind::Selective, "fg-selective-french", None)
+        );
+        assert_eq!(
+            parse("fg-optional-bonus-ost_01.bin"),
+            (RepackKind::Optional, "fg-optional-bonus-ost", Some(1))
+        );
+        assert_eq!(parse("setup.exe"), (RepackKind::Setup, "setup", None));
+        assert_eq!(
+            parse("fitgirl-bins.md5"),
+            (RepackKind::Md5, "fitgirl-bins", None)
+        );
+        assert_eq!(parse("Redist_x64.exe").0, RepackKind::Redist);
+        assert_eq!(parse("readme.txt"), (RepackKind::Other, "readme", None));
+    }
+
+    #[test]
+    fn parses_odd_names() {
+        assert_eq!(RepackFile::parse("MD5/QuickSFV.EXE").kind, RepackKind::Md5);
+        assert_eq!(RepackFile::parse("MD5\\QuickSFV.ini").kind, RepackKind::Md5);
+        // too long or not alphanumeric, not an extension
+        assert_eq!(
+            parse("name.site_--_"),
+            (RepackKind::Other, "name.site_--_", None)
+        );
+        assert_eq!(parse("name.part"), (RepackKind::Other, "name", None));
+        assert_eq!(
+            parse("name.partx.rar"),
+            (RepackKind::Other, "name.partx", None)
+        );
+        assert_eq!(
+            parse("name-99999999999.bin"),
+            (RepackKind::Other, "name-99999999999", None)
+        );
+        assert_eq!(parse(""), (RepackKind::Other, "", None));
+    }
+
+    #[test]
+    fn selects_optional_groups() {
+        let french = RepackFile::parse("fg-selective-french.bin");
+        assert!(!french.is_selected([]));
+        assert!(french.is_selected(["fg-selective-french"]));
+        assert!(french.is_selected(["fg-selective-french.bin"]));
+        assert!(!french.is_selected(["fg-selective-german"]));
+        assert!(RepackFile::parse("fg-01.bin").is_selected([]));
+
+        assert_eq!(french.label(), "French language");
+        assert_eq!(
+            RepackFile::parse("fg-optional-bonus-ost.bin").label(),
+            "Bonus ost"
+        );
+        assert_eq!(RepackFile::parse("fg-01.bin").label(), "fg");
+    }
+
+    #[test]
+    fn compares_naturally() {
+        let mut names = [
+            "part10", "part9", "part09", "part1", "part", "Part2", "part1a",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "Part2", "part", "part1", "part1a", "part09", "part9", "part10"
+            ]
+        );
+
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("a0001", "a2"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("a99999999999999999999999", "a100000000000000000000000"),
+            Ordering::Less
+        );
+        assert_eq!(natural_cmp("é2", "é10"), Ordering::Less);
+    }
+
+    #[test]
+    fn groups_parts_in_order() {
+        let groups = group_files(
+            [
+                "fg-10.bin",
+                "fg-2.bin",
+                "setup.exe",
+                "fg-1.bin",
+                "fg-selective-english.bin",
+            ],
+            |name| name,
+        );
+        assert_eq!(
+            groups.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["fg", "fg-selective-english", "setup"]
+        );
+        assert_eq!(groups["fg"], ["fg-1.bin", "fg-2.bin", "fg-10.bin"]);
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::errors::{DownloadError, ExtractError, ScrapeError};
+use crate::random::random_unit;
+use crate::runtime::sleep;
+
+/// Kinds of errors which may go away by trying again later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryKind {
+    /// [ExtractError::RateLimited]
+    RateLimited,
+    /// Network errors, timeouts, etc.
+    Request,
+    /// [ScrapeError::DDoSGuarded]
+    DDoSGuarded,
+}
+
+pub trait Retryable {
+    /// `None` for errors which are final, e.g. deleted files
+    fn retry_kind(&self) -> Option<RetryKind>;
+}
+
+impl Retryable for ExtractError {
+    fn retry_kind(&self) -> Option<RetryKind> {
+        match self {
+            Self::RateLimited => Some(RetryKind::RateLimited),
+            Self::RequestError(_) => Some(RetryKind::Request),
+            _ => None,
+        }
+    }
+}
+
+impl Retryable for ScrapeError {
+    fn retry_kind(&self) -> Option<RetryKind> {
+        match self {
+            Self::DDoSGuarded => Some(RetryKind::DDoSGuarded),
+            Self::RequestError(_) => Some(RetryKind::Request),
+            _ => None,
+        }
+    }
+}
+
+impl Retryable for DownloadError {
+    fn retry_kind(&self) -> Option<RetryKind> {
+        match self {
+            Self::RequestError(_) | Self::Incomplete { .. } => Some(RetryKind::Request),
+            Self::Extract(e) => e.retry_kind(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "aria2")]
+impl Retryable for crate::errors::Aria2Error {
+    fn retry_kind(&self) -> Option<RetryKind> {
+        match self {
+            Self::RequestError(_) => Some(RetryKind::Request),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Including the first attempt, `1` disables retrying
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each following retry
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of the delay to randomize, in `0.0..=1.0`
+    pub jitter: f64,
+    pub retry_on: Vec<RetryKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.5,
+            retry_on: vec![RetryKind::RateLimited, RetryKind::Request],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn should_retry(&self, error: &impl Retryable) -> bool {
+        error
+            .retry_kind()
+            .is_some_and(|kind| self.retry_on.contains(&kind))
+    }
+
+    /// Delay before the `retry`-th retry, starting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // scale into [1 - jitter, 1]
+        delay.mul_f64(1.0 - jitter * random_unit())
+    }
+
+    /// Run `f` until it succeeds, returns a final error, or attempts are used up.
+    pub async fn run<T, E>(&self, mut f: impl AsyncFnMut() -> Result<T, E>) -> Result<T, E>
+    where
+        E: Retryable + Display,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Err(e) if attempt < self.max_attempts && self.should_retry(&e) => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "attempt {attempt}/{} failed: {e}, retrying in {delay:?}",
+                        self.max_attempts
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(20),
+            jitter,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delay_doubles_until_max() {
+        let policy = policy(0.0);
+        let delays: Vec<_> = (1..=6).map(|retry| policy.delay(retry).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 20, 20]);
+        // the shift is capped instead of overflowing
+        assert_eq!(policy.delay(100), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn jitter_stays_in_bounds() {
+        let policy = policy(0.5);
+        let delays: Vec<_> = (0..200).map(|_| policy.delay(2)).collect();
+        assert!(
+            delays
+                .iter()
+                .all(|delay| (Duration::from_secs(2)..=Duration::from_secs(4)).contains(delay))
+        );
+        assert!(delays.iter().any(|&delay| delay != delays[0]));
+    }
+
+    #[test]
+    fn jitter_is_clamped() {
+        let delays: Vec<_> = (0..200).map(|_| policy(3.0).delay(1)).collect();
+        assert!(delays.iter().all(|&delay| delay <= Duration::from_secs(2)));
+        assert_eq!(policy(-1.0).delay(1), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn retries_only_listed_kinds() {
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(&ExtractError::RateLimited));
+        assert!(!policy.should_retry(&ExtractError::FileNotFound("a.rar".to_string())));
+        assert!(!policy.should_retry(&ScrapeError::DDoSGuarded));
+    }
+
+    #[cfg(feature = "compio")]
+    #[compio::test]
+    async fn run_stops_after_max_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        let mut attempts = 0;
+        let result: Result<(), _> = policy
+            .run(async || {
+                attempts += 1;
+                Err(ExtractError::RateLimited)
+            })
+            .await;
+        assert!(matches!(result, Err(ExtractError::RateLimited)));
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = policy
+            .run(async || {
+                attempts += 1;
+                Err(ExtractError::FileNotFound("a.rar".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(ExtractError::FileNotFound(_))));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+#[cfg(all(feature = "compio", feature = "tokio"))]
+compile_error!("features `compio` and `tokio` are mutually exclusive");
+#[cfg(not(any(feature = "compio", feature = "tokio")))]
+compile_error!("one of the features `compio` or `tokio` must be enabled");
+
+#[cfg(feature = "compio")]
+pub(crate) async fn sleep(duration: Duration) {
+    compio::time::sleep(duration).await;
+}
+
+#[cfg(all(feature = "tokio", not(feature = "compio")))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
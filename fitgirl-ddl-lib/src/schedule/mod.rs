@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+use url::Url;
+
+use crate::retry::{RetryKind, Retryable};
+use crate::runtime::sleep;
+
+/// How often waiting tasks check for a free slot
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerConfig {
+    /// Requests per second allowed to each host
+    pub rate: f64,
+    /// Requests which may be sent at once after being idle
+    pub burst: u32,
+    /// Concurrency never drops below this, even after being rate limited
+    pub min_concurrency: usize,
+    /// Concurrency to start with and never exceed
+    pub max_concurrency: usize,
+    /// Pause of a host after it reported rate limiting
+    pub cooldown: Duration,
+    /// Successful requests in a row before concurrency is raised by one
+    pub increase_after: u32,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            burst: 3,
+            min_concurrency: 1,
+            max_concurrency: 4,
+            cooldown: Duration::from_secs(30),
+            increase_after: 10,
+        }
+    }
+}
+
+/// Token bucket and adaptive concurrency limit per host.
+///
+/// Concurrency is halved and the host paused for [SchedulerConfig::cooldown]
+/// whenever a request is rate limited, then raised again slowly on success.
+///
+/// Cloning is cheap, clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    config: SchedulerConfig,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+#[derive(Debug)]
+struct HostState {
+    tokens: f64,
+    refilled_at: Instant,
+    cooldown_until: Option<Instant>,
+    limit: usize,
+    in_flight: usize,
+    successes: u32,
+}
+
+impl HostState {
+    fn new(config: &SchedulerConfig) -> Self {
+        Self {
+            tokens: config.burst.max(1) as f64,
+            refilled_at: Instant::now(),
+            cooldown_until: None,
+            limit: config.max_concurrency.max(1),
+            in_flight: 0,
+            successes: 0,
+        }
+    }
+
+    fn refill(&mut self, config: &SchedulerConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * config.rate).min(config.burst.max(1) as f64);
+        self.refilled_at = now;
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                hosts: Mutex::default(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.inner.config
+    }
+
+    /// Current concurrency limit of `host`
+    pub fn concurrency(&self, host: &str) -> usize {
+        self.hosts()
+            .get(host)
+            .map(|state| state.limit)
+            .unwrap_or(self.config().max_concurrency.max(1))
+    }
+
+    /// Run `f` once `host` has a free slot and a token, then adapt to its result.
+    pub async fn run<T, E: Retryable>(
+        &self,
+        host: &str,
+        f: impl AsyncFnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let permit = self.acquire(host).await;
+        let result = f().await;
+
+        let rate_limited = result
+            .as_ref()
+            .is_err_and(|e| e.retry_kind() == Some(RetryKind::RateLimited));
+        if rate_limited {
+            self.on_rate_limited(host);
+        } else if result.is_ok() {
+            self.on_success(host);
+        }
+
+        drop(permit);
+        result
+    }
+
+    async fn acquire(&self, host: &str) -> Permit<'_> {
+        loop {
+            match self.try_acquire(host) {
+                Ok(()) => {
+                    return Permit {
+                        scheduler: self,
+                        host: host.to_string(),
+                    };
+                }
+                Err(wait) => sleep(wait).await,
+            }
+        }
+    }
+
+    /// Take a slot and a token, or return how long to wait before trying again
+    fn try_acquire(&self, host: &str) -> Result<(), Duration> {
+        let config = self.config();
+        let now = Instant::now();
+        let mut hosts = self.hosts();
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| HostState::new(config));
+
+        if let Some(until) = state.cooldown_until {
+            if until > now {
+                return Err(until - now);
+            }
+            state.cooldown_until = None;
+        }
+        if state.in_flight >= state.limit {
+            return Err(POLL_INTERVAL);
+        }
+
+        state.refill(config, now);
+        if state.tokens < 1.0 {
+            let wait = if config.rate > 0.0 {
+                Duration::from_secs_f64((1.0 - state.tokens) / config.rate)
+            } else {
+                POLL_INTERVAL
+            };
+            return Err(wait.max(Duration::from_millis(10)));
+        }
+
+        state.tokens -= 1.0;
+        state.in_flight += 1;
+        Ok(())
+    }
+
+    fn on_success(&self, host: &str) {
+        let config = self.config();
+        let mut hosts = self.hosts();
+        let Some(state) = hosts.get_mut(host) else {
+            return;
+        };
+
+        state.successes += 1;
+        if state.successes >= config.increase_after && state.limit < config.max_concurrency {
+            state.limit += 1;
+            state.successes = 0;
+            debug!("raised concurrency of {host} to {}", state.limit);
+        }
+    }
+
+    fn on_rate_limited(&self, host: &str) {
+        let config = self.config();
+        let mut hosts = self.hosts();
+        let Some(state) = hosts.get_mut(host) else {
+            return;
+        };
+
+        let until = Instant::now() + config.cooldown;
+        // concurrent requests may all report the same rate limiting, only react once
+        if state
+            .cooldown_until
+            .is_some_and(|current| current >= until - config.cooldown / 2)
+        {
+            return;
+        }
+
+        state.cooldown_until = Some(until);
+        state.tokens = 0.0;
+        state.successes = 0;
+        state.limit = (state.limit / 2).max(config.min_concurrency.max(1));
+        info!(
+            "{host} rate limited, pausing for {:?} with concurrency {}",
+            config.cooldown, state.limit
+        );
+    }
+
+    fn hosts(&self) -> MutexGuard<'_, HashMap<String, HostState>> {
+        self.inner.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Releases the slot when dropped, also when the request future is cancelled
+struct Permit<'a> {
+    scheduler: &'a Scheduler,
+    host: String,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = self.scheduler.hosts().get_mut(&self.host) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// Key of `url` in a [Scheduler], the host name if it can be parsed
+pub fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(rate: f64, burst: u32) -> Scheduler {
+        Scheduler::new(SchedulerConfig {
+            rate,
+            burst,
+            max_concurrency: 100,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn refills_at_rate_up_to_burst() {
+        let config = SchedulerConfig {
+            rate: 2.0,
+            burst: 3,
+            ..Default::default()
+        };
+        let mut state = HostState::new(&config);
+        let start = state.refilled_at;
+        state.tokens = 0.0;
+
+        state.refill(&config, start + Duration::from_millis(500));
+        assert_eq!(state.tokens, 1.0);
+        state.refill(&config, start + Duration::from_secs(1));
+        assert_eq!(state.tokens, 2.0);
+        state.refill(&config, start + Duration::from_secs(60));
+        assert_eq!(state.tokens, 3.0);
+    }
+
+    #[test]
+    fn burst_then_waits_for_a_token() {
+        let scheduler = scheduler(0.5, 3);
+        for _ in 0..3 {
+            assert_eq!(scheduler.try_acquire("a.test"), Ok(()));
+        }
+
+        let wait = scheduler.try_acquire("a.test").unwrap_err();
+        assert!(wait > Duration::from_millis(1900) && wait <= Duration::from_secs(2));
+        // hosts have their own bucket
+        assert_eq!(scheduler.try_acquire("b.test"), Ok(()));
+    }
+
+    #[test]
+    fn waits_for_a_slot() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_concurrency: 1,
+            ..Default::default()
+        });
+        let permit = scheduler.try_acquire("a.test").map(|_| Permit {
+            scheduler: &scheduler,
+            host: "a.test".to_string(),
+        });
+        assert!(permit.is_ok());
+        assert_eq!(scheduler.try_acquire("a.test"), Err(POLL_INTERVAL));
+
+        drop(permit);
+        assert_eq!(scheduler.try_acquire("a.test"), Ok(()));
+    }
+
+    #[test]
+    fn rate_limit_pauses_and_halves_concurrency() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            max_concurrency: 8,
+            cooldown: Duration::from_secs(30),
+            ..Default::default()
+        });
+        scheduler.try_acquire("a.test").unwrap();
+
+        scheduler.on_rate_limited("a.test");
+        assert_eq!(scheduler.concurrency("a.test"), 4);
+        let wait = scheduler.try_acquire("a.test").unwrap_err();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+
+        // concurrent requests reporting the same rate limiting
+        scheduler.on_rate_limited("a.test");
+        assert_eq!(scheduler.concurrency("a.test"), 4);
+        assert_eq!(scheduler.try_acquire("b.test"), Ok(()));
+    }
+
+    #[test]
+    fn concurrency_recovers_on_success() {
+        let scheduler = Scheduler::new(SchedulerConfig {
+            min_concurrency: 1,
+            max_concurrency: 2,
+            cooldown: Duration::ZERO,
+            increase_after: 3,
+            ..Default::default()
+        });
+        scheduler.try_acquire("a.test").unwrap();
+        scheduler.on_rate_limited("a.test");
+        assert_eq!(scheduler.concurrency("a.test"), 1);
+
+        for _ in 0..2 {
+            scheduler.on_success("a.test");
+        }
+        assert_eq!(scheduler.concurrency("a.test"), 1);
+        scheduler.on_success("a.test");
+        assert_eq!(scheduler.concurrency("a.test"), 2);
+        for _ in 0..3 {
+            scheduler.on_success("a.test");
+        }
+        assert_eq!(scheduler.concurrency("a.test"), 2);
+    }
+
+    #[test]
+    fn host_key_is_host_name() {
+        assert_eq!(
+            host_key("https://fuckingfast.co/abc#a.rar"),
+            "fuckingfast.co"
+        );
+        assert_eq!(host_key("not a url"), "not a url");
+    }
+}
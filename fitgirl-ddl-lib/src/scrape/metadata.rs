@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset};
+use scraper::{ElementRef, Html, Node, Selector};
+
+use crate::errors::ScrapeError;
+use crate::repack::RepackFile;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameMetadata {
+    pub title: String,
+    pub genres: Vec<String>,
+    pub companies: Vec<String>,
+    pub languages: Vec<String>,
+    /// e.g. `31.8 GB`
+    pub original_size: Option<String>,
+    /// e.g. `from 10.1 GB [Selective Download]`
+    pub repack_size: Option<String>,
+    /// e.g. `up to 57 GB`, from the "HDD space after installation" feature
+    pub install_size: Option<String>,
+    /// Publish date of the repack post
+    pub release_date: Option<DateTime<FixedOffset>>,
+    pub cover_image: Option<String>,
+    pub repack_features: Vec<String>,
+    /// Descriptions from the "Selective Download" section,
+    /// keyed by [RepackFile::group] of `fg-optional-*` and `fg-selective-*` files
+    pub selective_downloads: BTreeMap<String, String>,
+}
+
+impl GameMetadata {
+    /// Description of an optional group, or a readable form of its name if the page has none
+    pub fn describe_group(&self, group: &str) -> String {
+        let file = RepackFile::parse(group);
+        self.selective_downloads
+            .get(&file.group)
+            .cloned()
+            .unwrap_or_else(|| file.label())
+    }
+}
+
+pub(crate) fn parse_metadata(document: &Html) -> Result<GameMetadata, ScrapeError> {
+    let title_selector = Selector::parse("h1.entry-title")?;
+    let date_selector = Selector::parse("time.entry-date")?;
+    let cover_selector = Selector::parse("div.entry-content img")?;
+    let paragraph_selector = Selector::parse("div.entry-content p")?;
+    let heading_selector = Selector::parse("div.entry-content h3")?;
+    let section_selector =
+        Selector::parse("div.entry-content h3, div.entry-content h4, div.su-spoiler-title")?;
+
+    let mut metadata = GameMetadata {
+        title: document
+            .select(&title_selector)
+            .next()
+            .map(|tag| tag.text().collect::<String>().trim().to_string())
+            .unwrap_or_default(),
+        release_date: document
+            .select(&date_selector)
+            .next()
+            .and_then(|tag| tag.attr("datetime"))
+            .and_then(|datetime| DateTime::parse_from_rfc3339(datetime).ok()),
+        cover_image: document
+            .select(&cover_selector)
+            .next()
+            .and_then(|tag| tag.attr("src"))
+            .map(str::to_string),
+        ..Default::default()
+    };
+
+    let info_lines = document
+        .select(&paragraph_selector)
+        .map(text_lines)
+        .find(|lines| {
+            lines
+                .iter()
+                .any(|line| line.starts_with("Genres") || line.starts_with("Repack Size"))
+        })
+        .unwrap_or_default();
+
+    for line in info_lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        if key.starts_with("genres") {
+            metadata.genres = split_list(value, &[',']);
+        } else if key.starts_with("compan") {
+            metadata.companies = split_list(value, &[',', '/']);
+        } else if key.starts_with("language") {
+            metadata.languages = split_list(value, &['/', ',']);
+        } else if key.starts_with("original size") {
+            metadata.original_size = Some(value.to_string());
+        } else if key.starts_with("repack size") {
+            metadata.repack_size = Some(value.to_string());
+        }
+    }
+
+    if let Some(features) = document
+        .select(&heading_selector)
+        .find(|h3| h3.text().collect::<String>().contains("Repack Features"))
+        .and_then(next_element)
+        .filter(|list| list.value().name() == "ul")
+    {
+        metadata.repack_features = features
+            .child_elements()
+            .map(|li| li.text().collect::<String>().trim().to_string())
+            .filter(|feature| !feature.is_empty())
+            .collect();
+        metadata.install_size = metadata
+            .repack_features
+            .iter()
+            .find(|feature| feature.starts_with("HDD space after installation"))
+            .and_then(|feature| feature.split_once(':'))
+            .map(|(_, size)| size.trim().to_string());
+    }
+
+    // either a heading followed by the list, or a spoiler title followed by its content
+    if let Some(heading) = document.select(&section_selector).find(|tag| {
+        tag.text()
+            .collect::<String>()
+            .contains("Selective Download")
+    }) {
+        metadata.selective_downloads = heading
+            .next_siblings()
+            .filter_map(ElementRef::wrap)
+            .take_while(|element| !matches!(element.value().name(), "h2" | "h3" | "h4"))
+            .flat_map(text_lines)
+            .filter_map(|line| parse_selective_line(&line))
+            .collect();
+    }
+
+    Ok(metadata)
+}
+
+/// `fg-selective-french.bin - French audio` => `(fg-selective-french, French audio)`
+///
+/// Lines naming several files, or only a filename, have no usable description.
+fn parse_selective_line(line: &str) -> Option<(String, String)> {
+    let is_separator =
+        |c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '(' | ')' | '[' | ']');
+
+    let (files, rest): (Vec<_>, Vec<_>) = line
+        .split(is_separator)
+        .filter(|word| !word.is_empty())
+        .partition(|word| {
+            let lower = word.to_ascii_lowercase();
+            lower.starts_with("fg-optional") || lower.starts_with("fg-selective")
+        });
+    let [file] = files[..] else {
+        return None;
+    };
+
+    let description = rest
+        .join(" ")
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '—' | '.'))
+        .to_string();
+    (!description.is_empty()).then(|| (RepackFile::parse(file).group, description))
+}
+
+/// Text of an element, split at `<br>` and block elements
+pub(crate) fn text_lines(element: ElementRef<'_>) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for node in element.descendants() {
+        match node.value() {
+            Node::Text(text) => lines.last_mut().unwrap().push_str(text),
+            Node::Element(e) if matches!(e.name(), "br" | "p" | "li" | "div") => {
+                lines.push(String::new())
+            }
+            _ => (),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+pub(crate) fn next_element(element: ElementRef<'_>) -> Option<ElementRef<'_>> {
+    element.next_siblings().find_map(ElementRef::wrap)
+}
+
+fn split_list(value: &str, separators: &[char]) -> Vec<String> {
+    value
+        .split(separators)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_PAGE: &str = include_str!("testdata/game.html");
+
+    fn metadata(html: &str) -> GameMetadata {
+        parse_metadata(&Html::parse_document(html)).unwrap()
+    }
+
+    #[test]
+    fn parses_info_of_game_page() {
+        let metadata = metadata(GAME_PAGE);
+        assert_eq!(
+            metadata.title,
+            "Some Game: Deluxe Edition – v1.2.3 + 2 DLCs"
+        );
+        assert_eq!(
+            metadata.release_date.map(|date| date.to_rfc3339()),
+            Some("2024-03-05T18:24:17+03:00".to_string())
+        );
+        assert_eq!(
+            metadata.cover_image.as_deref(),
+            Some("https://i3.imageban.ru/out/2024/03/05/cover.jpg")
+        );
+        assert_eq!(metadata.genres, ["Action", "RPG", "Open world"]);
+        assert_eq!(metadata.companies, ["Studio One", "Publisher Two"]);
+        assert_eq!(metadata.languages, ["ENG", "MULTI12"]);
+        assert_eq!(metadata.original_size.as_deref(), Some("31.8 GB"));
+        assert_eq!(
+            metadata.repack_size.as_deref(),
+            Some("from 10.1 GB [Selective Download]")
+        );
+        assert_eq!(metadata.install_size.as_deref(), Some("up to 57 GB"));
+        assert_eq!(metadata.repack_features.len(), 7);
+        assert_eq!(
+            metadata.repack_features[1],
+            "100% Lossless & MD5 Perfect: all files are identical to originals after installation"
+        );
+    }
+
+    #[test]
+    fn leaves_out_missing_info() {
+        let metadata = metadata(
+            r#"<h1 class="entry-title"> Bare Game </h1>
+            <div class="entry-content"><p>Some game about some things.</p></div>"#,
+        );
+        assert_eq!(
+            metadata,
+            GameMetadata {
+                title: "Bare Game".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn maps_selective_downloads_to_groups() {
+        let metadata = metadata(GAME_PAGE);
+        assert_eq!(
+            metadata.selective_downloads,
+            BTreeMap::from([
+                (
+                    "fg-optional-bonus-content".to_string(),
+                    "Artbook & Soundtrack".to_string()
+                ),
+                (
+                    "fg-selective-french".to_string(),
+                    "French audio".to_string()
+                ),
+                (
+                    "fg-selective-german".to_string(),
+                    "German audio".to_string()
+                ),
+            ])
+        );
+
+        assert_eq!(
+            metadata.describe_group("fg-selective-french"),
+            "French audio"
+        );
+        assert_eq!(
+            metadata.describe_group("fg-selective-german.bin"),
+            "German audio"
+        );
+        // named together with another file, no description of its own
+        assert_eq!(
+            metadata.describe_group("fg-selective-japanese"),
+            "Japanese language"
+        );
+        assert_eq!(metadata.describe_group("fg-01"), "fg");
+    }
+
+    #[test]
+    fn labels_groups_without_selective_section() {
+        // drop the spoiler of the section, up to the next heading
+        let title = GAME_PAGE.find(">Selective Download</div>").unwrap();
+        let start = GAME_PAGE[..title]
+            .rfind("<div class=\"su-spoiler ")
+            .unwrap();
+        let end = GAME_PAGE.find("<h3>Game Description</h3>").unwrap();
+        let page = format!("{}{}", &GAME_PAGE[..start], &GAME_PAGE[end..]);
+
+        let metadata = metadata(&page);
+        assert!(metadata.selective_downloads.is_empty());
+        assert_eq!(metadata.install_size.as_deref(), Some("up to 57 GB"));
+        assert_eq!(
+            metadata.describe_group("fg-selective-french"),
+            "French language"
+        );
+        assert_eq!(
+            metadata.describe_group("fg-optional-bonus-content"),
+            "Bonus content"
+        );
+    }
+
+    #[test]
+    fn parses_selective_lines() {
+        assert_eq!(
+            parse_selective_line("fg-selective-french.bin - French audio"),
+            Some((
+                "fg-selective-french".to_string(),
+                "French audio".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_selective_line("[fg-optional-4k-textures.bin]: 4K textures (12 GB)"),
+            Some((
+                "fg-optional-4k-textures".to_string(),
+                "4K textures 12 GB".to_string()
+            ))
+        );
+        assert_eq!(parse_selective_line("fg-selective-french.bin"), None);
+        assert_eq!(
+            parse_selective_line("fg-selective-english.bin, fg-selective-japanese.bin"),
+            None
+        );
+        assert_eq!(
+            parse_selective_line("You can skip the following files:"),
+            None
+        );
+    }
+}
@@ -1,5 +1,9 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
 use http::header::COOKIE;
-use scraper::Selector;
+use scraper::{Html, Selector};
+use tracing::debug;
 use wreq::{Method, Uri};
 
 #[cfg(feature = "compio")]
@@ -8,91 +12,253 @@ use compio::runtime::spawn_blocking;
 use tokio::task::spawn_blocking;
 
 use crate::errors::ScrapeError;
-use crate::{FITGIRL_COOKIES, HTTP_CLIENT};
+use crate::hoster::{FuckingFast, Hoster};
+use crate::torrent::{MagnetLink, parse_torrent_links};
+use crate::{FitgirlClient, default_client};
+
+mod metadata;
+
+pub use metadata::GameMetadata;
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
 pub struct GameInfo {
     pub path_part: String,
+    /// Same as `hoster_links[FuckingFast::NAME]`, in page order and possibly repeated,
+    /// see [GameInfo::unique_links]
     pub fuckingfast_links: Vec<String>,
+    /// Links grouped by [Hoster::name]
+    pub hoster_links: BTreeMap<String, Vec<String>>,
+    pub magnets: Vec<MagnetLink>,
+    /// Links to `.torrent` files
+    pub torrent_files: Vec<String>,
+    pub metadata: GameMetadata,
+}
+
+impl GameInfo {
+    /// `fuckingfast_links` in page order, without the duplicates
+    /// [check_completeness](crate::check::check_completeness) reports
+    pub fn unique_links(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.fuckingfast_links
+            .iter()
+            .filter(|link| seen.insert(link.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Links of every hoster in `hosters`, one per file and from the first hoster listing it.
+    ///
+    /// Files are told apart by the filename in the fragment, or by the whole link without one.
+    pub fn mirror_links(&self, hosters: &[Arc<dyn Hoster>]) -> Vec<String> {
+        let mut seen = HashSet::new();
+        hosters
+            .iter()
+            .filter_map(|hoster| self.hoster_links.get(hoster.name()))
+            .flatten()
+            .filter(|link| {
+                seen.insert(link.split_once('#').map_or(link.as_str(), |(_, name)| name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Number of links of all hosters, repeated ones included
+    pub fn link_count(&self) -> usize {
+        self.hoster_links.values().map(Vec::len).sum()
+    }
 }
 
+/// Scrape with [default_client].
 pub async fn scrape_game(url: impl AsRef<str>) -> Result<GameInfo, ScrapeError> {
-    let url: Uri = url.as_ref().parse()?;
+    default_client().scrape_game(url).await
+}
+
+/// Parse a game page saved from browser, with hosters of [default_client].
+///
+/// See [FitgirlClient::parse_game_page].
+pub fn parse_game_page(html: &str, url: Option<&str>) -> Result<GameInfo, ScrapeError> {
+    default_client().parse_game_page(html, url)
+}
+
+impl FitgirlClient {
+    pub async fn scrape_game(&self, url: impl AsRef<str>) -> Result<GameInfo, ScrapeError> {
+        let url: Uri = url.as_ref().parse()?;
+        let path_part = path_slug(&url)?;
+
+        let mut req = wreq::Request::new(Method::GET, url);
+        if let Some(cookies) = self.fitgirl_cookies() {
+            req.headers_mut().insert(COOKIE, cookies);
+        }
+
+        let resp = self
+            .http()
+            .execute(req)
+            .await
+            .map_err(|e| ScrapeError::RequestError(e.to_string()))?;
+
+        if resp.status() == 403 {
+            return Err(ScrapeError::DDoSGuarded);
+        }
+
+        let document = resp
+            .text()
+            .await
+            .map_err(|e| ScrapeError::RequestError(e.to_string()))?;
+
+        let hosters = self.hosters().to_vec();
+        spawn_blocking(move || {
+            parse_document(&Html::parse_document(&document), path_part, &hosters)
+        })
+        .await
+        .map_err(|_| ScrapeError::JoinError)?
+    }
+
+    /// Parse a game page without network access, e.g. saved from browser when ddos-guard blocks us.
+    ///
+    /// If `url` is `None`, it's taken from the canonical link of the page.
+    /// This is CPU-bound, consider running it in a blocking task.
+    pub fn parse_game_page(&self, html: &str, url: Option<&str>) -> Result<GameInfo, ScrapeError> {
+        let document = Html::parse_document(html);
 
-    let path_slug = url
+        let url: Uri = match url {
+            Some(url) => url.parse()?,
+            None => canonical_url(&document)?
+                .ok_or(ScrapeError::UnexpectedURL)?
+                .parse()?,
+        };
+
+        parse_document(&document, path_slug(&url)?, self.hosters())
+    }
+}
+
+fn path_slug(url: &Uri) -> Result<String, ScrapeError> {
+    Ok(url
         .path()
         .split("/")
         .find(|s| !s.is_empty())
         .ok_or(ScrapeError::UnexpectedURL)?
-        .to_string();
+        .to_string())
+}
 
-    let mut req = wreq::Request::new(Method::GET, url);
-    if let Some(cookies) = FITGIRL_COOKIES.get() {
-        req.headers_mut().insert(COOKIE, cookies.clone());
-    }
+fn canonical_url(document: &Html) -> Result<Option<String>, ScrapeError> {
+    let canonical_selector = Selector::parse(r#"link[rel="canonical"]"#)?;
+    let og_url_selector = Selector::parse(r#"meta[property="og:url"]"#)?;
 
-    let resp = HTTP_CLIENT
-        .execute(req)
-        .await
-        .map_err(|e| ScrapeError::RequestError(e.to_string()))?;
+    Ok(document
+        .select(&canonical_selector)
+        .find_map(|tag| tag.attr("href"))
+        .or_else(|| {
+            document
+                .select(&og_url_selector)
+                .find_map(|tag| tag.attr("content"))
+        })
+        .map(str::to_string))
+}
 
-    if resp.status() == 403 {
-        return Err(ScrapeError::DDoSGuarded);
-    }
+fn parse_document(
+    document: &Html,
+    path_part: String,
+    hosters: &[Arc<dyn Hoster>],
+) -> Result<GameInfo, ScrapeError> {
+    let metadata = metadata::parse_metadata(document)?;
+    let (magnets, torrent_files) = parse_torrent_links(document)?;
 
-    let document = resp
-        .text()
-        .await
-        .map_err(|e| ScrapeError::RequestError(e.to_string()))?;
+    let mut hoster_links = BTreeMap::new();
+    let mut first_error = None;
+    for hoster in hosters {
+        match hoster.discover_links(document) {
+            Ok(links) if !links.is_empty() => {
+                hoster_links.insert(hoster.name().to_string(), links);
+            }
+            Ok(_) => debug!("no links for {}", hoster.name()),
+            Err(e) => {
+                debug!("no links for {}: {e}", hoster.name());
+                first_error.get_or_insert(e);
+            }
+        }
+    }
 
-    let fuckingfast_links = spawn_blocking(move || parse_html(document))
-        .await
-        .map_err(|_| ScrapeError::JoinError)??;
+    // torrents are still usable without any DDL
+    if hoster_links.is_empty() && magnets.is_empty() && torrent_files.is_empty() {
+        return Err(first_error.unwrap_or(ScrapeError::FuckingFastSourceMissing));
+    }
 
     Ok(GameInfo {
-        path_part: path_slug,
-        fuckingfast_links,
+        path_part,
+        fuckingfast_links: hoster_links
+            .get(FuckingFast::NAME)
+            .cloned()
+            .unwrap_or_default(),
+        hoster_links,
+        magnets,
+        torrent_files,
+        metadata,
     })
 }
 
-fn parse_html(document: impl AsRef<str>) -> Result<Vec<String>, ScrapeError> {
-    let document = document.as_ref();
-    let document = scraper::Html::parse_document(document);
-
-    let file_hoster = Selector::parse("div.entry-content ul > li:nth-child(2) > a")?;
-    let tags = document.select(&file_hoster).collect::<Vec<_>>();
-
-    let single_tag = match tags.len() {
-        0 => return Err(ScrapeError::FuckingFastSourceMissing)?,
-        _ => tags[0],
-    };
-
-    let file_hoster_spolier = Selector::parse(
-        "div.entry-content ul > li:nth-child(2) > div.su-spoiler > div.su-spoiler-content",
-    )?;
-
-    let spoiler_content = document.select(&file_hoster_spolier).collect::<Vec<_>>();
-    match &*spoiler_content {
-        &[] => Ok(vec![
-            single_tag
-                .attr("href")
-                .ok_or(ScrapeError::FuckingFastSourceMissing)?
-                .to_string(),
-        ]),
-        spoilers => {
-            let mut results = Vec::new();
-            for spoiler in spoilers {
-                results.extend(
-                    spoiler
-                        .select(&Selector::parse("a")?)
-                        .filter_map(|tag| tag.attr("href"))
-                        .map(str::to_string),
-                );
-            }
-            results.sort_unstable();
-            results.dedup();
-            Ok(results)
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_PAGE: &str = include_str!("testdata/game.html");
+
+    #[test]
+    fn parses_saved_game_page() {
+        let game = FitgirlClient::default()
+            .parse_game_page(GAME_PAGE, None)
+            .unwrap();
+        assert_eq!(game.path_part, "some-game");
+        assert_eq!(
+            game.fuckingfast_links,
+            [
+                "https://fuckingfast.co/aaaa1111#Some_Game_--_fitgirl-repacks.site_--_.part1.rar",
+                "https://fuckingfast.co/bbbb2222#Some_Game_--_fitgirl-repacks.site_--_.part2.rar",
+                "https://fuckingfast.co/cccc3333#fg-selective-french.bin",
+                "https://fuckingfast.co/dddd4444#fg-optional-bonus-content.bin",
+            ]
+        );
+        assert_eq!(
+            game.hoster_links.keys().collect::<Vec<_>>(),
+            [FuckingFast::NAME]
+        );
+        assert_eq!(game.link_count(), 4);
+        assert_eq!(
+            game.mirror_links(FitgirlClient::default().hosters()),
+            game.fuckingfast_links
+        );
+        assert_eq!(game.magnets.len(), 1);
+        assert_eq!(
+            game.magnets[0].info_hash,
+            "0123456789abcdef0123456789abcdef01234567"
+        );
+        assert_eq!(
+            game.torrent_files,
+            ["https://paste.fitgirl-repacks.site/torrents/Some_Game.torrent"]
+        );
+        assert_eq!(game.metadata.original_size.as_deref(), Some("31.8 GB"));
+
+        let game = FitgirlClient::default()
+            .parse_game_page(GAME_PAGE, Some("https://fitgirl-repacks.site/other-game/"))
+            .unwrap();
+        assert_eq!(game.path_part, "other-game");
+    }
+
+    #[test]
+    fn rejects_pages_without_links() {
+        let result = FitgirlClient::default().parse_game_page(
+            r#"<link rel="canonical" href="https://fitgirl-repacks.site/some-game/">
+            <div class="entry-content"><p>Nothing here</p></div>"#,
+            None,
+        );
+        assert!(
+            matches!(result, Err(ScrapeError::UnrecognizedLayout { .. })),
+            "{result:?}"
+        );
+        assert!(
+            FitgirlClient::default()
+                .parse_game_page("<p>no canonical link</p>", None)
+                .is_err()
+        );
     }
 }
@@ -0,0 +1,138 @@
+use chrono::{DateTime, FixedOffset};
+use http::header::COOKIE;
+use scraper::{ElementRef, Html, Selector};
+use tracing::warn;
+use wreq::{Method, Uri};
+
+#[cfg(feature = "compio")]
+use compio::runtime::spawn_blocking;
+#[cfg(feature = "tokio")]
+use tokio::task::spawn_blocking;
+
+use crate::errors::SearchError;
+use crate::{FitgirlClient, default_client};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchEntry {
+    pub title: String,
+    pub href: String,
+    pub date: DateTime<FixedOffset>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default)]
+pub struct SearchPage {
+    pub entries: Vec<SearchEntry>,
+    /// Current page, starts from 1
+    pub page: usize,
+    pub total_pages: usize,
+    pub has_next_page: bool,
+}
+
+/// Search with [default_client].
+pub async fn search_games(query: &str, page: usize) -> Result<SearchPage, SearchError> {
+    default_client().search_games(query, page).await
+}
+
+impl FitgirlClient {
+    /// Search games by keyword, `page` starts from 1.
+    pub async fn search_games(&self, query: &str, page: usize) -> Result<SearchPage, SearchError> {
+        let page = page.max(1);
+
+        let mut url = self.fitgirl_base().join(&format!("page/{page}/"))?;
+        url.query_pairs_mut().append_pair("s", query);
+        let uri: Uri = url.as_str().parse()?;
+
+        let mut req = wreq::Request::new(Method::GET, uri);
+        if let Some(cookies) = self.fitgirl_cookies() {
+            req.headers_mut().insert(COOKIE, cookies);
+        }
+
+        let resp = self
+            .http()
+            .execute(req)
+            .await
+            .map_err(|e| SearchError::RequestError(e.to_string()))?;
+
+        if resp.status() == 403 {
+            return Err(SearchError::DDoSGuarded);
+        }
+
+        let document = resp
+            .text()
+            .await
+            .map_err(|e| SearchError::RequestError(e.to_string()))?;
+
+        spawn_blocking(move || parse_search_page(document, page))
+            .await
+            .map_err(|_| SearchError::JoinError)?
+    }
+}
+
+fn parse_search_page(document: impl AsRef<str>, page: usize) -> Result<SearchPage, SearchError> {
+    let html = Html::parse_document(document.as_ref());
+
+    let article_selector = Selector::parse("article.post")?;
+    let date_selector = Selector::parse("span.entry-date > a > time")?;
+    let title_selector = Selector::parse("h1.entry-title > a")?;
+    let page_numbers_selector = Selector::parse(".page-numbers")?;
+    let next_page_selector = Selector::parse("a.next.page-numbers")?;
+
+    let entries = html
+        .select(&article_selector)
+        .filter_map(|article| parse_article(article, &date_selector, &title_selector))
+        .filter(|SearchEntry { title, .. }| !title.starts_with("Updates Digest"))
+        .collect();
+
+    let total_pages = html
+        .select(&page_numbers_selector)
+        .filter_map(|tag| {
+            tag.text()
+                .collect::<String>()
+                .trim()
+                .replace(',', "")
+                .parse::<usize>()
+                .ok()
+        })
+        .max()
+        .unwrap_or(page)
+        .max(page);
+    let has_next_page = html.select(&next_page_selector).next().is_some() || page < total_pages;
+
+    Ok(SearchPage {
+        entries,
+        page,
+        total_pages,
+        has_next_page,
+    })
+}
+
+fn parse_article(
+    article: ElementRef<'_>,
+    date_selector: &Selector,
+    title_selector: &Selector,
+) -> Option<SearchEntry> {
+    let Some(a_tag) = article.select(title_selector).next() else {
+        warn!("skipping article without title");
+        return None;
+    };
+    let title = a_tag.text().collect::<String>();
+    let Some(href) = a_tag.attr("href") else {
+        warn!("skipping article without link: {title}");
+        return None;
+    };
+
+    let date = article
+        .select(date_selector)
+        .next()
+        .and_then(|tag| tag.attr("datetime"))
+        .and_then(|datetime| DateTime::parse_from_rfc3339(datetime).ok())
+        .unwrap_or_default();
+
+    Some(SearchEntry {
+        title,
+        href: href.to_string(),
+        date,
+    })
+}
@@ -0,0 +1,218 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+
+use crate::extract::DDL;
+use crate::probe::total_size;
+use crate::scrape::GameMetadata;
+
+/// Download and install sizes of a selection of files
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpacePlan {
+    /// Sum of probed part sizes, or the repack size if some parts are not probed
+    pub download: Option<u64>,
+    /// Whether `download` is summed from every selected part
+    pub download_exact: bool,
+    /// From "HDD space after installation"
+    pub install: Option<u64>,
+}
+
+impl SpacePlan {
+    /// Plan for the selected `ddls` of a game.
+    ///
+    /// Without probing, the repack size only covers files every install needs.
+    pub fn new<'a>(metadata: &GameMetadata, ddls: impl IntoIterator<Item = &'a DDL>) -> Self {
+        let probed = total_size(ddls);
+        Self {
+            download: probed.or_else(|| metadata.repack_size.as_deref().and_then(parse_size)),
+            download_exact: probed.is_some(),
+            install: metadata.install_size.as_deref().and_then(parse_size),
+        }
+    }
+
+    /// Bytes needed with the archives kept until the installation finishes
+    pub fn required(&self) -> u64 {
+        self.download.unwrap_or_default() + self.install.unwrap_or_default()
+    }
+
+    /// Free space of the filesystem holding `dir`, and whether it covers [Self::required]
+    pub fn check(&self, dir: impl AsRef<Path>) -> io::Result<(u64, bool)> {
+        let available = free_space(dir)?;
+        Ok((available, available >= self.required()))
+    }
+}
+
+impl Display for SpacePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.download {
+            Some(size) if self.download_exact => write!(f, "download {}", format_size(size))?,
+            Some(size) => write!(f, "download at least {}", format_size(size))?,
+            None => write!(f, "download size unknown")?,
+        }
+        match self.install {
+            Some(size) => write!(f, ", install {}", format_size(size)),
+            None => write!(f, ", install size unknown"),
+        }
+    }
+}
+
+/// First size in text like `from 10.1 GB [Selective Download]`, units are binary like Windows shows.
+///
+/// A comma is a decimal point in `10,1 GB`, but a thousands separator in `1,024.5 MB`.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(end);
+        rest = after;
+
+        let unit = after
+            .trim_start()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        let shift = match unit.as_str() {
+            "KB" | "KIB" => 10,
+            "MB" | "MIB" => 20,
+            "GB" | "GIB" => 30,
+            "TB" | "TIB" => 40,
+            _ => continue,
+        };
+        let number = if number.contains('.') {
+            number.replace(',', "")
+        } else {
+            number.replace(',', ".")
+        };
+        if let Ok(number) = number.parse::<f64>() {
+            return Some((number * (1u64 << shift) as f64) as u64);
+        }
+    }
+    None
+}
+
+/// `1536` => `1.50 KiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.2} {}", UNITS[unit])
+    }
+}
+
+/// Bytes available to the current user on the filesystem holding `path`.
+///
+/// `path` may not exist yet, its nearest existing ancestor is checked.
+pub fn free_space(path: impl AsRef<Path>) -> io::Result<u64> {
+    let path = std::path::absolute(path)?;
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+    available_bytes(existing)
+}
+
+#[cfg(unix)]
+// field types of `statvfs` differ between platforms
+#[allow(clippy::unnecessary_cast)]
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid nul-terminated C string which outlives the call,
+    // and `stat` points to writable memory of the size of `statvfs`
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `statvfs` returned 0, so it filled in `stat`
+    let stat = unsafe { stat.assume_init() };
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut available = 0;
+    // SAFETY: `path` is a nul-terminated wide string which outlives the call,
+    // `available` is a valid `u64` to write to and the unused outputs may be null
+    if unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(
+            parse_size("from 10.5 GB [Selective Download]"),
+            Some(GIB * 21 / 2)
+        );
+        assert_eq!(parse_size("10,5 GB"), Some(GIB * 21 / 2));
+        assert_eq!(
+            parse_size("1,024.5 MB"),
+            Some((1024.5 * (1 << 20) as f64) as u64)
+        );
+        assert_eq!(parse_size("1 TiB"), Some(GIB << 10));
+        assert_eq!(parse_size("512kb"), Some(512 << 10));
+        // numbers without a unit are skipped
+        assert_eq!(parse_size("2 parts, 3 GB"), Some(3 * GIB));
+    }
+
+    #[test]
+    fn rejects_sizes_without_units() {
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("42"), None);
+        assert_eq!(parse_size("10 PB"), None);
+        assert_eq!(parse_size("1.2.3 GB"), None);
+        assert_eq!(parse_size("1,2,3 GB"), None);
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.50 KiB");
+        assert_eq!(format_size(GIB * 21 / 2), "10.50 GiB");
+        assert_eq!(format_size(u64::MAX), "16777216.00 TiB");
+    }
+
+    #[test]
+    fn formats_parsed_sizes() {
+        for text in ["1.50 KiB", "10.50 GiB", "3.25 TiB"] {
+            assert_eq!(parse_size(text).map(format_size).as_deref(), Some(text));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Fixtures shared by tests, and a local HTTP server standing in for hosters, aria2 and JDownloader
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "compio")]
+pub use server::*;
+
+use crate::extract::DDL;
+
+/// A resolved link of `filename`, fetched at the unix epoch
+pub fn ddl(filename: &str) -> DDL {
+    DDL {
+        filename: filename.to_string(),
+        direct_link: format!("https://dl.example/{filename}"),
+        source_url: format!("https://fuckingfast.co/abc#{filename}"),
+        fetched_at: Default::default(),
+        expires_at: None,
+        probe: None,
+    }
+}
+
+/// Empty directory unique to one test, removed when dropped
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "fitgirl-ddl-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(feature = "compio")]
+mod server {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::sync::mpsc::{Receiver, channel};
+    use std::thread;
+
+    /// A request received by [serve]
+    #[derive(Debug, Clone)]
+    pub struct Request {
+        pub method: String,
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: String,
+    }
+
+    impl Request {
+        /// Value of the header `name`, case-insensitive
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// An answer of [serve], plain strings are sent as `200 OK` JSON
+    #[derive(Debug, Clone)]
+    pub struct Response {
+        pub status: u16,
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+
+    impl Response {
+        pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+            Self {
+                status,
+                headers: Vec::new(),
+                body: body.into(),
+            }
+        }
+
+        pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+            self.headers.push((name.to_string(), value.into()));
+            self
+        }
+    }
+
+    impl From<String> for Response {
+        fn from(body: String) -> Self {
+            Self::new(200, body).header("Content-Type", "application/json")
+        }
+    }
+
+    /// Answer requests on a free local port with the response returned by `respond`,
+    /// `None` closes the connection without answering.
+    ///
+    /// Connections are served concurrently, as a client downloading in segments opens several.
+    ///
+    /// Returns `http://127.0.0.1:<port>` and the requests in the order they arrived.
+    pub fn serve<R: Into<Response>>(
+        respond: impl Fn(&Request) -> Option<R> + Send + Sync + 'static,
+    ) -> (String, Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = channel();
+        let respond = Arc::new(respond);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    return;
+                };
+                let (respond, sender) = (respond.clone(), sender.clone());
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream);
+                    while let Some(request) = read_request(&mut reader) {
+                        let response = respond(&request).map(Into::into);
+                        // the test is over once nobody receives
+                        if sender.send(request).is_err() {
+                            return;
+                        }
+                        let Some(response) = response else {
+                            return;
+                        };
+                        if write_response(reader.get_mut(), &response).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        (base, receiver)
+    }
+
+    /// A free local port nobody listens on
+    pub fn closed_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut request_line = line.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).ok()?;
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.to_string(), value.trim().to_string()));
+            }
+        }
+
+        let mut request = Request {
+            method,
+            path,
+            headers,
+            body: String::new(),
+        };
+        let length = match request.header("content-length") {
+            Some(length) => length.parse().ok()?,
+            None => 0,
+        };
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        request.body = String::from_utf8(body).ok()?;
+        Some(request)
+    }
+
+    fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} Status\r\n", response.status);
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", response.body.len()));
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&response.body)
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use crate::errors::TorrentError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Value<'a>>),
+    Dict(BTreeMap<&'a [u8], Value<'a>>),
+}
+
+impl<'a> Value<'a> {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Bytes(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|b| std::str::from_utf8(b).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[Value<'a>]> {
+        match self {
+            Self::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match self {
+            Self::Dict(d) => d.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a single bencoded value, trailing bytes are rejected.
+pub fn decode(input: &[u8]) -> Result<Value<'_>, TorrentError> {
+    let mut decoder = Decoder { input, pos: 0 };
+    let value = decoder.value(0)?;
+    if decoder.pos != input.len() {
+        return Err(decoder.error());
+    }
+    Ok(value)
+}
+
+const MAX_DEPTH: usize = 64;
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn error(&self) -> TorrentError {
+        TorrentError::InvalidBencode(self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value<'a>, TorrentError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error());
+        }
+
+        match self.peek().ok_or_else(|| self.error())? {
+            b'i' => {
+                self.pos += 1;
+                let int = self.read_until(b'e')?;
+                let int = std::str::from_utf8(int)
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| self.error())?;
+                Ok(Value::Int(int))
+            }
+            b'l' => {
+                self.pos += 1;
+                let mut list = Vec::new();
+                while self.peek() != Some(b'e') {
+                    list.push(self.value(depth + 1)?);
+                }
+                self.pos += 1;
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.pos += 1;
+                let mut dict = BTreeMap::new();
+                while self.peek() != Some(b'e') {
+                    let key = self.bytes()?;
+                    let value = self.value(depth + 1)?;
+                    dict.insert(key, value);
+                }
+                self.pos += 1;
+                Ok(Value::Dict(dict))
+            }
+            b'0'..=b'9' => Ok(Value::Bytes(self.bytes()?)),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], TorrentError> {
+        let len = self.read_until(b':')?;
+        let len: usize = std::str::from_utf8(len)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error())?;
+
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.input.len())
+            .ok_or_else(|| self.error())?;
+        let bytes = &self.input[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Read bytes until `delimiter`, consuming the delimiter.
+    fn read_until(&mut self, delimiter: u8) -> Result<&'a [u8], TorrentError> {
+        let start = self.pos;
+        let len = self.input[start..]
+            .iter()
+            .position(|&b| b == delimiter)
+            .ok_or_else(|| self.error())?;
+        self.pos = start + len + 1;
+        Ok(&self.input[start..start + len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &Value<'_>, output: &mut Vec<u8>) {
+        match value {
+            Value::Int(i) => output.extend(format!("i{i}e").as_bytes()),
+            Value::Bytes(bytes) => {
+                output.extend(format!("{}:", bytes.len()).as_bytes());
+                output.extend(*bytes);
+            }
+            Value::List(list) => {
+                output.push(b'l');
+                list.iter().for_each(|value| encode(value, output));
+                output.push(b'e');
+            }
+            Value::Dict(dict) => {
+                output.push(b'd');
+                for (key, value) in dict {
+                    encode(&Value::Bytes(key), output);
+                    encode(value, output);
+                }
+                output.push(b'e');
+            }
+        }
+    }
+
+    fn error_at(input: &[u8]) -> usize {
+        match decode(input) {
+            Err(TorrentError::InvalidBencode(pos)) => pos,
+            other => panic!("expected an error for {input:?}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_scalars() {
+        assert_eq!(decode(b"i42e").unwrap(), Value::Int(42));
+        assert_eq!(decode(b"i-7e").unwrap(), Value::Int(-7));
+        assert_eq!(decode(b"4:spam").unwrap(), Value::Bytes(b"spam"));
+        assert_eq!(decode(b"0:").unwrap(), Value::Bytes(b""));
+    }
+
+    #[test]
+    fn decodes_nested() {
+        let value = decode(b"d4:infod4:name4:game5:filesld6:lengthi3eeee4:spaml1:ai1eee").unwrap();
+        let info = value.get("info").unwrap();
+        assert_eq!(info.get("name").and_then(Value::as_str), Some("game"));
+        let files = info.get("files").and_then(Value::as_list).unwrap();
+        assert_eq!(files[0].get("length").and_then(Value::as_int), Some(3));
+        assert_eq!(
+            value.get("spam").and_then(Value::as_list),
+            Some(&[Value::Bytes(b"a"), Value::Int(1)][..])
+        );
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(Value::Int(1).get("info"), None);
+    }
+
+    #[test]
+    fn round_trips() {
+        let inputs: [&[u8]; 5] = [
+            b"i0e",
+            b"3:\xff\x00\x01",
+            b"le",
+            b"de",
+            b"d1:ali1ei-2e0:e1:bd1:cdeee",
+        ];
+        for input in inputs {
+            let value = decode(input).unwrap();
+            let mut output = Vec::new();
+            encode(&value, &mut output);
+            assert_eq!(output, input);
+            assert_eq!(decode(&output).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(error_at(b""), 0);
+        assert_eq!(error_at(b"x"), 0);
+        assert_eq!(error_at(b"i12"), 1);
+        assert_eq!(error_at(b"iabce"), 5);
+        assert_eq!(error_at(b"ie"), 2);
+        assert_eq!(error_at(b"5:abc"), 2);
+        assert_eq!(error_at(b"-1:a"), 0);
+        assert_eq!(error_at(b"l"), 1);
+        assert_eq!(error_at(b"li1e"), 4);
+        assert_eq!(error_at(b"d3:keye"), 6);
+        assert_eq!(error_at(b"di1ei2ee"), 1);
+        assert_eq!(error_at(b"i1ei2e"), 3);
+    }
+
+    #[test]
+    fn rejects_oversized_lengths() {
+        error_at(b"99999999999999999999999:a");
+        error_at(b"18446744073709551615:a");
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let depth = MAX_DEPTH + 1;
+        let mut nested = vec![b'l'; depth];
+        nested.extend(vec![b'e'; depth]);
+        assert!(decode(&nested).is_ok());
+
+        let mut too_deep = vec![b'l'; depth + 1];
+        too_deep.extend(vec![b'e'; depth + 1]);
+        assert_eq!(error_at(&too_deep), depth);
+    }
+}
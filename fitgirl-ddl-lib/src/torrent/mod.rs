@@ -0,0 +1,341 @@
+use scraper::{Html, Selector};
+use url::Url;
+use wreq::Uri;
+
+use crate::errors::{ScrapeError, TorrentError};
+use crate::repack::RepackFile;
+use crate::{FitgirlClient, default_client};
+
+mod bencode;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagnetLink {
+    pub uri: String,
+    /// Value after `urn:btih:`, hex or base32
+    pub info_hash: String,
+    pub display_name: Option<String>,
+    pub trackers: Vec<String>,
+}
+
+impl MagnetLink {
+    /// Returns `None` if `uri` is not a BitTorrent magnet link.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+        if url.scheme() != "magnet" {
+            return None;
+        }
+
+        let mut info_hash = None;
+        let mut display_name = None;
+        let mut trackers = Vec::new();
+        for (key, value) in url.query_pairs() {
+            match &*key {
+                "xt" => {
+                    let Some((urn, hash)) = value.rsplit_once(':') else {
+                        continue;
+                    };
+                    if urn.eq_ignore_ascii_case("urn:btih") {
+                        info_hash = Some(hash.to_ascii_lowercase());
+                    }
+                }
+                "dn" => display_name = Some(value.into_owned()),
+                "tr" => trackers.push(value.into_owned()),
+                _ => (),
+            }
+        }
+
+        Some(Self {
+            uri: uri.to_string(),
+            info_hash: info_hash?,
+            display_name,
+            trackers,
+        })
+    }
+}
+
+/// Magnets and `.torrent` file links of a game page
+pub(crate) fn parse_torrent_links(
+    document: &Html,
+) -> Result<(Vec<MagnetLink>, Vec<String>), ScrapeError> {
+    let link_selector = Selector::parse("div.entry-content a[href]")?;
+
+    let mut magnets: Vec<MagnetLink> = Vec::new();
+    let mut torrent_files: Vec<String> = Vec::new();
+    for href in document
+        .select(&link_selector)
+        .filter_map(|tag| tag.attr("href"))
+    {
+        if href.starts_with("magnet:") {
+            if let Some(magnet) = MagnetLink::parse(href)
+                && !magnets.iter().any(|m| m.info_hash == magnet.info_hash)
+            {
+                magnets.push(magnet);
+            }
+        } else if is_torrent_file(href) && !torrent_files.iter().any(|t| t == href) {
+            torrent_files.push(href.to_string());
+        }
+    }
+
+    Ok((magnets, torrent_files))
+}
+
+fn is_torrent_file(href: &str) -> bool {
+    Url::parse(href)
+        .map(|url| url.path().to_ascii_lowercase().ends_with(".torrent"))
+        .unwrap_or_default()
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorrentInfo {
+    pub name: String,
+    pub files: Vec<TorrentFile>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TorrentFile {
+    /// Starts from 1, as aria2 `select-file` expects
+    pub index: usize,
+    /// Path inside the torrent, joined with `/`
+    pub path: String,
+    pub length: u64,
+}
+
+impl TorrentFile {
+    pub fn filename(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+impl TorrentInfo {
+    /// Decode a `.torrent` file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TorrentError> {
+        let root = bencode::decode(bytes)?;
+        let info = root.get("info").ok_or(TorrentError::FieldMissing("info"))?;
+        let name = info
+            .get("name.utf-8")
+            .or_else(|| info.get("name"))
+            .and_then(|name| name.as_str())
+            .ok_or(TorrentError::FieldMissing("info.name"))?
+            .to_string();
+
+        let Some(files) = info.get("files").and_then(|files| files.as_list()) else {
+            // single-file torrent
+            let length = info
+                .get("length")
+                .and_then(|length| length.as_int())
+                .ok_or(TorrentError::FieldMissing("info.length"))?;
+            return Ok(Self {
+                files: vec![TorrentFile {
+                    index: 1,
+                    path: name.clone(),
+                    length: length.max(0) as u64,
+                }],
+                name,
+            });
+        };
+
+        let files = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                let length = file
+                    .get("length")
+                    .and_then(|length| length.as_int())
+                    .ok_or(TorrentError::FieldMissing("info.files.length"))?;
+                let path = file
+                    .get("path.utf-8")
+                    .or_else(|| file.get("path"))
+                    .and_then(|path| path.as_list())
+                    .ok_or(TorrentError::FieldMissing("info.files.path"))?
+                    .iter()
+                    .map(|segment| segment.as_str().unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                Ok(TorrentFile {
+                    index: i + 1,
+                    path,
+                    length: length.max(0) as u64,
+                })
+            })
+            .collect::<Result<_, TorrentError>>()?;
+
+        Ok(Self { name, files })
+    }
+
+    /// Indices of files to download for selected DDL groups.
+    ///
+    /// `fg-optional-*` and `fg-selective-*` files are selected only if their group is,
+    /// other files are required by the installer and always selected.
+    pub fn select_files<'a>(&self, groups: impl IntoIterator<Item = &'a str>) -> Vec<usize> {
+        let groups: Vec<_> = groups.into_iter().collect();
+
+        self.files
+            .iter()
+            .filter(|file| RepackFile::parse(file.filename()).is_selected(groups.iter().copied()))
+            .map(|file| file.index)
+            .collect()
+    }
+}
+
+/// Fetch and decode a `.torrent` file with [default_client].
+pub async fn fetch_torrent(url: impl AsRef<str>) -> Result<TorrentInfo, TorrentError> {
+    default_client().fetch_torrent(url).await
+}
+
+impl FitgirlClient {
+    pub async fn fetch_torrent(&self, url: impl AsRef<str>) -> Result<TorrentInfo, TorrentError> {
+        let uri: Uri = url.as_ref().parse()?;
+        let resp = self
+            .http()
+            .get(uri)
+            .send()
+            .await
+            .map_err(|e| TorrentError::RequestError(e.to_string()))?;
+
+        // ddos-guard and missing files answer with html, which isn't bencode
+        if !resp.status().is_success() {
+            return Err(TorrentError::RequestError(format!(
+                "{} returned {}",
+                url.as_ref(),
+                resp.status()
+            )));
+        }
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| TorrentError::RequestError(e.to_string()))?;
+
+        TorrentInfo::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "compio")]
+    use crate::testing::{Response, serve};
+
+    const MULTI_FILE: &[u8] = b"d8:announce3:url4:infod5:filesld6:lengthi100e4:pathl\
+        9:setup.exeeed6:lengthi2048e4:pathl\
+        23:fg-selective-french.binee\
+        d6:lengthi-5e4:pathl3:dir5:a.bineee4:name4:Gamee\
+        e";
+
+    #[test]
+    fn decodes_multi_file() {
+        let info = TorrentInfo::from_bytes(MULTI_FILE).unwrap();
+        assert_eq!(info.name, "Game");
+        let paths: Vec<_> = info.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["setup.exe", "fg-selective-french.bin", "dir/a.bin"]);
+        assert_eq!(info.files[1].index, 2);
+        assert_eq!(info.files[1].length, 2048);
+        // negative lengths are clamped
+        assert_eq!(info.files[2].length, 0);
+        assert_eq!(info.files[2].filename(), "a.bin");
+    }
+
+    #[test]
+    fn decodes_single_file() {
+        let info = TorrentInfo::from_bytes(b"d4:infod6:lengthi7e4:name5:a.bin12:piece lengthi1eee")
+            .unwrap();
+        assert_eq!(
+            info.files,
+            [TorrentFile {
+                index: 1,
+                path: "a.bin".to_string(),
+                length: 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn prefers_utf8_names() {
+        let info = TorrentInfo::from_bytes(
+            b"d4:infod6:lengthi1e4:name1:?10:name.utf-85:\xc3\xa9t\xc3\xa9ee",
+        )
+        .unwrap();
+        assert_eq!(info.name, "été");
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        assert!(matches!(
+            TorrentInfo::from_bytes(b"de"),
+            Err(TorrentError::FieldMissing("info"))
+        ));
+        assert!(matches!(
+            TorrentInfo::from_bytes(b"d4:infod6:lengthi1eee"),
+            Err(TorrentError::FieldMissing("info.name"))
+        ));
+        assert!(matches!(
+            TorrentInfo::from_bytes(b"d4:infod4:name1:aee"),
+            Err(TorrentError::FieldMissing("info.length"))
+        ));
+        assert!(matches!(
+            TorrentInfo::from_bytes(b"d4:infod5:filesld4:pathl1:aeee4:name1:aee"),
+            Err(TorrentError::FieldMissing("info.files.length"))
+        ));
+        assert!(matches!(
+            TorrentInfo::from_bytes(b"d4:info"),
+            Err(TorrentError::InvalidBencode(_))
+        ));
+    }
+
+    #[test]
+    fn selects_files_of_groups() {
+        let info = TorrentInfo::from_bytes(MULTI_FILE).unwrap();
+        assert_eq!(info.select_files([]), [1, 3]);
+        assert_eq!(info.select_files(["fg-selective-french"]), [1, 2, 3]);
+    }
+
+    #[test]
+    fn parses_magnets() {
+        let magnet = MagnetLink::parse(
+            "magnet:?xt=urn:btih:ABCDEF&dn=Some+Game&tr=udp%3A%2F%2Ftracker%3A80&tr=http%3A%2F%2Fb",
+        )
+        .unwrap();
+        assert_eq!(magnet.info_hash, "abcdef");
+        assert_eq!(magnet.display_name.as_deref(), Some("Some Game"));
+        assert_eq!(magnet.trackers, ["udp://tracker:80", "http://b"]);
+
+        assert_eq!(MagnetLink::parse("magnet:?dn=no-hash"), None);
+        assert_eq!(MagnetLink::parse("magnet:?xt=urn:sha1:abc"), None);
+        assert_eq!(
+            MagnetLink::parse("https://example.com/?xt=urn:btih:abc"),
+            None
+        );
+        assert_eq!(MagnetLink::parse("not a url"), None);
+    }
+
+    #[cfg(feature = "compio")]
+    #[compio::test]
+    async fn fetches_only_successful_responses() {
+        let (base, requests) = serve(|request| {
+            Some(match request.path.as_str() {
+                "/game.torrent" => Response::new(200, MULTI_FILE),
+                _ => Response::new(403, "<html>DDoS-Guard</html>"),
+            })
+        });
+        let client = FitgirlClient::default();
+
+        let info = client
+            .fetch_torrent(format!("{base}/game.torrent"))
+            .await
+            .unwrap();
+        assert_eq!(info.name, "Game");
+        assert_eq!(requests.recv().unwrap().method, "GET");
+
+        let result = client
+            .fetch_torrent(format!("{base}/guarded.torrent"))
+            .await;
+        assert!(
+            matches!(&result, Err(TorrentError::RequestError(e)) if e.contains("403")),
+            "{result:?}"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use futures_util::StreamExt as _;
+use tracing::{debug, warn};
+
+#[cfg(feature = "compio")]
+use compio::runtime::spawn_blocking;
+#[cfg(feature = "tokio")]
+use tokio::task::spawn_blocking;
+
+use crate::errors::VerifyError;
+
+/// Folder of repacks holding the checksum file
+pub const MD5_DIR: &str = "MD5";
+
+/// An entry of a `.md5` file
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    /// Relative path as written in the `.md5` file, with `/` as separator
+    pub path: String,
+    /// Lowercase hex
+    pub md5: String,
+}
+
+impl Checksum {
+    pub fn filename(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Parse lines like `d41d8cd98f00b204e9800998ecf8427e *fg-01.bin`, comments are skipped.
+pub fn parse_md5_file(text: &str) -> Vec<Checksum> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with([';', '#']))
+        .filter_map(|line| {
+            let (md5, path) = line.split_once(char::is_whitespace)?;
+            if md5.len() != 32 || !md5.bytes().all(|b| b.is_ascii_hexdigit()) {
+                debug!("skipping md5 line: {line}");
+                return None;
+            }
+            let path = path.trim().trim_start_matches('*').replace('\\', "/");
+            Some(Checksum {
+                path,
+                md5: md5.to_ascii_lowercase(),
+            })
+        })
+        .collect()
+}
+
+/// Name of the checksum file in [MD5_DIR] of repacks
+pub const MD5_FILE: &str = "fitgirl-bins.md5";
+
+/// Find the `.md5` file in `dir/MD5`, or directly in `dir`.
+///
+/// [MD5_FILE] is preferred, otherwise the first `.md5` file by name.
+pub fn find_md5_file(dir: impl AsRef<Path>) -> io::Result<Option<PathBuf>> {
+    let dir = dir.as_ref();
+    for candidate in [dir.join(MD5_DIR), dir.to_path_buf()] {
+        let entries = match std::fs::read_dir(&candidate) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let mut found = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("md5"))
+                && path.is_file()
+            {
+                found.push(path);
+            }
+        }
+
+        // read_dir order differs between platforms and runs
+        found.sort();
+        let index = found
+            .iter()
+            .position(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(MD5_FILE))
+            })
+            .unwrap_or(0);
+        if index < found.len() {
+            return Ok(Some(found.swap_remove(index)));
+        }
+    }
+    Ok(None)
+}
+
+/// Result of checking files against a `.md5` file
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub verified: Vec<String>,
+    pub corrupted: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.corrupted.is_empty() && self.missing.is_empty()
+    }
+
+    /// Filenames of corrupted and missing parts
+    pub fn bad_parts(&self) -> impl Iterator<Item = &str> {
+        self.corrupted
+            .iter()
+            .chain(&self.missing)
+            .map(|path| path.rsplit('/').next().unwrap_or(path))
+    }
+
+    /// Links of `fuckingfast_links` serving a bad part, matched by their fragment
+    pub fn select_links<'a>(&self, fuckingfast_links: &'a [String]) -> Vec<&'a String> {
+        fuckingfast_links
+            .iter()
+            .filter(|link| {
+                link.split('#')
+                    .nth(1)
+                    .is_some_and(|filename| self.bad_parts().any(|bad| bad == filename))
+            })
+            .collect()
+    }
+}
+
+impl Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "all {} files verified", self.verified.len());
+        }
+        for path in &self.corrupted {
+            writeln!(f, "corrupted: {path}")?;
+        }
+        for path in &self.missing {
+            writeln!(f, "missing: {path}")?;
+        }
+        Ok(())
+    }
+}
+
+enum Status {
+    Verified,
+    Corrupted,
+    Missing,
+}
+
+/// Check files in `dir` against its `.md5` file, hashing up to `workers` files at once.
+pub async fn verify_dir(
+    dir: impl AsRef<Path>,
+    workers: usize,
+) -> Result<VerifyReport, VerifyError> {
+    let dir = dir.as_ref().to_path_buf();
+
+    let (md5_file, text) = {
+        let dir = dir.clone();
+        spawn_blocking(move || {
+            let md5_file = find_md5_file(&dir)?
+                .ok_or_else(|| VerifyError::Md5FileMissing(dir.display().to_string()))?;
+            let text = std::fs::read_to_string(&md5_file)?;
+            Ok::<_, VerifyError>((md5_file, text))
+        })
+        .await
+        .map_err(|_| VerifyError::JoinError)??
+    };
+    let checksums = parse_md5_file(&text);
+    if checksums.is_empty() {
+        warn!("no checksum found in {md5_file:?}");
+    }
+    let base = md5_file.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let results: Vec<_> = futures_util::stream::iter(checksums)
+        .map(|checksum| {
+            let candidates = [base.join(&checksum.path), dir.join(checksum.filename())];
+            async move {
+                let status = spawn_blocking(move || check_file(&candidates, &checksum.md5))
+                    .await
+                    .map_err(|_| VerifyError::JoinError)??;
+                Ok::<_, VerifyError>((checksum.path, status))
+            }
+        })
+        .buffer_unordered(workers.max(1))
+        .collect()
+        .await;
+
+    let mut report = VerifyReport::default();
+    for result in results {
+        let (path, status) = result?;
+        match status {
+            Status::Verified => report.verified.push(path),
+            Status::Corrupted => report.corrupted.push(path),
+            Status::Missing => report.missing.push(path),
+        }
+    }
+    report.verified.sort();
+    report.corrupted.sort();
+    report.missing.sort();
+
+    Ok(report)
+}
+
+fn check_file(candidates: &[PathBuf], expected: &str) -> io::Result<Status> {
+    let Some(path) = candidates.iter().find(|path| path.is_file()) else {
+        return Ok(Status::Missing);
+    };
+
+    let mut file = File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            n => context.consume(&buffer[..n]),
+        }
+    }
+
+    let actual = format!("{:x}", context.finalize());
+    if actual == expected {
+        Ok(Status::Verified)
+    } else {
+        debug!("{path:?}: expected {expected}, got {actual}");
+        Ok(Status::Corrupted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const EMPTY_MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+    #[test]
+    fn parses_md5_lines() {
+        let text = format!(
+            "; generated by QuickSFV\r\n\
+             # comment\r\n\
+             \r\n\
+             {} *fg-01.bin\r\n\
+             {EMPTY_MD5}  MD5\\setup part.exe\n",
+            EMPTY_MD5.to_ascii_uppercase()
+        );
+        assert_eq!(
+            parse_md5_file(&text),
+            [
+                Checksum {
+                    path: "fg-01.bin".to_string(),
+                    md5: EMPTY_MD5.to_string(),
+                },
+                Checksum {
+                    path: "MD5/setup part.exe".to_string(),
+                    md5: EMPTY_MD5.to_string(),
+                },
+            ]
+        );
+        assert_eq!(parse_md5_file(&text)[1].filename(), "setup part.exe");
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let text = format!(
+            "{EMPTY_MD5}\n\
+             d41d8cd98f00b204e9800998ecf8427 *short.bin\n\
+             z41d8cd98f00b204e9800998ecf8427e *not-hex.bin\n\
+             {EMPTY_MD5}0 *long.bin\n\
+             {EMPTY_MD5} *ok.bin"
+        );
+        let checksums = parse_md5_file(&text);
+        assert_eq!(checksums.len(), 1);
+        assert_eq!(checksums[0].path, "ok.bin");
+        assert!(parse_md5_file("").is_empty());
+    }
+
+    #[test]
+    fn prefers_known_md5_file() {
+        let dir = TempDir::new();
+        let md5_dir = dir.join(MD5_DIR);
+        std::fs::create_dir_all(&md5_dir).unwrap();
+        assert_eq!(find_md5_file(&dir).unwrap(), None);
+
+        std::fs::write(dir.join("b.md5"), "").unwrap();
+        std::fs::write(dir.join("a.md5"), "").unwrap();
+        assert_eq!(find_md5_file(&dir).unwrap(), Some(dir.join("a.md5")));
+
+        std::fs::write(md5_dir.join("a.md5"), "").unwrap();
+        std::fs::write(md5_dir.join(MD5_FILE), "").unwrap();
+        assert_eq!(find_md5_file(&dir).unwrap(), Some(md5_dir.join(MD5_FILE)));
+    }
+}